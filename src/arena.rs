@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+/// The play-field: the walls enclosing it, the kill zone below it and the physics world
+/// the game runs in.
+///
+/// The physics is stepped in [`FixedUpdate`], once per tick by the duration of the tick.
+/// An app adding its own [`RapierPhysicsPlugin`] before this one keeps it, along with
+/// its [`RapierConfiguration`].
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<RapierPhysicsPlugin<NoUserData>>() {
+            app.insert_resource(RapierConfiguration {
+                gravity: Vec2::ZERO,
+                timestep_mode: TimestepMode::Fixed {
+                    dt: Time::<Fixed>::default().timestep().as_secs_f32(),
+                    substeps: 1,
+                },
+                ..RapierConfiguration::new(100.0)
+            })
+            .add_plugins(
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).in_fixed_schedule(),
+            );
+        }
+
+        app.init_resource::<PlayField>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                FixedUpdate,
+                sync_physics_timestep_system.before(PhysicsSet::SyncBackend),
+            );
+    }
+}
+
+/// Size of the play-field, in the pixels of the game world. It is the same at any window
+/// size, the camera scales it to fit the window.
+pub const PLAY_FIELD_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+/// Thickness of the walls, enough that nothing goes through them in a single step.
+pub const WALL_THICKNESS: f32 = 100.0;
+/// Collision group of the balls, the only bodies the bottom wall lets through into the
+/// kill zone. The balls do not collide with each other either.
+pub const BALL_GROUP: Group = Group::GROUP_1;
+
+/// The rectangle the game is played in, centered on the origin.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PlayField {
+    pub size: Vec2,
+}
+
+impl Default for PlayField {
+    fn default() -> Self {
+        Self {
+            size: PLAY_FIELD_SIZE,
+        }
+    }
+}
+
+impl PlayField {
+    pub fn half_size(&self) -> Vec2 {
+        self.size / 2.0
+    }
+
+    /// Whether `point` is within the play-field, edges included.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let half_size = self.half_size();
+        point.x.abs() <= half_size.x && point.y.abs() <= half_size.y
+    }
+}
+
+/// One of the walls around the play-field.
+#[derive(Component)]
+pub struct Wall;
+
+/// A sensor below the play-field, a ball entering it is lost.
+#[derive(Component)]
+pub struct KillZone;
+
+/// A wall, a fixed box the balls bounce off and the paddles are stopped by.
+#[derive(Bundle)]
+pub struct WallBundle {
+    pub wall: Wall,
+    pub transform: TransformBundle,
+    pub body: RigidBody,
+    pub collider: Collider,
+}
+
+impl WallBundle {
+    pub fn new(center: Vec2, size: Vec2) -> Self {
+        Self {
+            wall: Wall,
+            transform: TransformBundle::from_transform(Transform::from_translation(
+                center.extend(0.0),
+            )),
+            body: RigidBody::Fixed,
+            collider: Collider::cuboid(size.x / 2.0, size.y / 2.0),
+        }
+    }
+}
+
+fn setup(mut commands: Commands, play_field: Res<PlayField>) {
+    // Walls outside the play-field, the side ones long enough to close the corners
+    let half_size = play_field.half_size();
+    let offset = half_size + WALL_THICKNESS / 2.0;
+    let horizontal = Vec2::new(play_field.size.x + 2.0 * WALL_THICKNESS, WALL_THICKNESS);
+    let vertical = Vec2::new(WALL_THICKNESS, play_field.size.y);
+    commands.spawn(WallBundle::new(Vec2::new(-offset.x, 0.0), vertical));
+    commands.spawn(WallBundle::new(Vec2::new(offset.x, 0.0), vertical));
+    commands.spawn(WallBundle::new(Vec2::new(0.0, offset.y), horizontal));
+
+    // The bottom wall keeps the paddles in but lets the balls fall into the kill zone
+    let bottom = Vec2::new(0.0, -offset.y);
+    commands.spawn((
+        WallBundle::new(bottom, horizontal),
+        CollisionGroups::new(Group::ALL, Group::ALL - BALL_GROUP),
+    ));
+    commands.spawn((
+        KillZone,
+        TransformBundle::from_transform(Transform::from_translation(bottom.extend(0.0))),
+        RigidBody::Fixed,
+        Collider::cuboid(horizontal.x / 2.0, horizontal.y / 2.0),
+        Sensor,
+    ));
+}
+
+/// Keeps the physics step in line with the tick rate, which may change at runtime.
+fn sync_physics_timestep_system(time: Res<Time<Fixed>>, mut config: ResMut<RapierConfiguration>) {
+    let dt = time.timestep().as_secs_f32();
+    if let TimestepMode::Fixed { dt: physics_dt, .. } = &mut config.timestep_mode {
+        if *physics_dt != dt {
+            *physics_dt = dt;
+        }
+    }
+}
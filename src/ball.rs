@@ -0,0 +1,240 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    arena::BALL_GROUP, block::BlockShape, collision::BallHitPlayer, configure_game_sets,
+    movement::PlayerMotion, player::Player, simulation::InterpolatedTransform, GameSet,
+};
+
+/// Keeps the balls at a constant speed, sends them off the paddles at an angle set by
+/// where they hit and remembers which player touched each of them last.
+pub struct BallPlugin;
+
+impl Plugin for BallPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.add_event::<BallLost>()
+            .init_resource::<BallSpeed>()
+            .add_systems(
+                FixedUpdate,
+                keep_ball_speed_system.in_set(GameSet::Movement),
+            )
+            .add_systems(
+                FixedUpdate,
+                (deflect_balls_system, track_last_hit_system).in_set(GameSet::Collisions),
+            );
+    }
+}
+
+pub const BALL_RADIUS: f32 = 10.0;
+pub const BALL_SPEED: f32 = 400.0;
+/// Where a ball is served from, relative to the center of its player's paddle.
+pub const BALL_SERVE_OFFSET: Vec2 = Vec2::new(0.0, 50.0);
+/// Angle from the paddle's normal a ball leaves at when it hits one of the paddle's ends.
+pub const MAX_DEFLECTION_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+/// Share of the paddle's velocity at the point of contact passed on to the ball.
+pub const PADDLE_VELOCITY_TRANSFER: f32 = 0.5;
+/// Smallest vertical speed of a ball, as a fraction of its speed, so it never ends up
+/// bouncing from side to side forever.
+pub const MIN_VERTICAL_SPEED: f32 = 0.25;
+
+#[derive(Component)]
+pub struct Ball;
+
+/// A ball added during the game on top of the players' own, losing it costs no life.
+#[derive(Component)]
+pub struct ExtraBall;
+
+/// Speed every ball is kept at, in pixels per second.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct BallSpeed(pub f32);
+
+impl Default for BallSpeed {
+    fn default() -> Self {
+        Self(BALL_SPEED)
+    }
+}
+
+/// The player who last sent the ball flying, credited for the bricks it breaks.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct LastHitBy(pub Option<Entity>);
+
+/// Sent when a ball falls out of the bottom of the play-field into the kill zone.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BallLost {
+    pub ball: Entity,
+}
+
+/// The ball, a frictionless dynamic body that keeps its energy on every bounce and goes
+/// through the bottom wall and the other balls.
+#[derive(Bundle)]
+pub struct BallBundle {
+    pub ball: Ball,
+    pub last_hit_by: LastHitBy,
+    pub mesh: MaterialMesh2dBundle<ColorMaterial>,
+    pub body: RigidBody,
+    pub collider: Collider,
+    pub restitution: Restitution,
+    pub friction: Friction,
+    pub gravity_scale: GravityScale,
+    pub locked_axes: LockedAxes,
+    pub ccd: Ccd,
+    pub velocity: Velocity,
+    pub active_events: ActiveEvents,
+    pub collision_groups: CollisionGroups,
+    pub interpolated: InterpolatedTransform,
+}
+
+impl BallBundle {
+    pub fn new(position: Vec2, material: Handle<ColorMaterial>, meshes: &mut Assets<Mesh>) -> Self {
+        Self {
+            ball: Ball,
+            last_hit_by: LastHitBy::default(),
+            mesh: MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(BALL_RADIUS))),
+                material,
+                transform: Transform::from_translation(position.extend(0.0)),
+                ..default()
+            },
+            body: RigidBody::Dynamic,
+            collider: Collider::ball(BALL_RADIUS),
+            restitution: Restitution {
+                coefficient: 1.0,
+                combine_rule: CoefficientCombineRule::Max,
+            },
+            friction: Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            gravity_scale: GravityScale(0.0),
+            locked_axes: LockedAxes::ROTATION_LOCKED,
+            ccd: Ccd::enabled(),
+            velocity: Velocity::linear(Vec2::new(0.5, 1.0).normalize() * BALL_SPEED),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            collision_groups: CollisionGroups::new(BALL_GROUP, Group::ALL - BALL_GROUP),
+            interpolated: InterpolatedTransform::default(),
+        }
+    }
+}
+
+fn keep_ball_speed_system(mut query: Query<&mut Velocity, With<Ball>>, speed: Res<BallSpeed>) {
+    for mut velocity in query.iter_mut() {
+        // Contacts with the moving paddle would otherwise add or drain energy
+        velocity.linvel = with_min_vertical_speed(velocity.linvel.normalize_or_zero() * speed.0);
+    }
+}
+
+/// Steepens `velocity` to at least [`MIN_VERTICAL_SPEED`], keeping its length and the
+/// direction it moves in along each axis.
+fn with_min_vertical_speed(velocity: Vec2) -> Vec2 {
+    let speed = velocity.length();
+    let min_vertical = speed * MIN_VERTICAL_SPEED;
+    if velocity.y.abs() >= min_vertical {
+        return velocity;
+    }
+
+    let y = if velocity.y < 0.0 {
+        -min_vertical
+    } else {
+        min_vertical
+    };
+    let x = (speed * speed - y * y).sqrt().copysign(velocity.x);
+    Vec2::new(x, y)
+}
+
+/// Sends a ball hitting a paddle off along the paddle's normal when it hits its center,
+/// up to [`MAX_DEFLECTION_ANGLE`] further to the side the closer it hits to an end.
+/// A tilted paddle tilts the normal with it, and the paddle's motion and spin drag the
+/// ball along.
+pub(crate) fn deflect_balls_system(
+    mut hits: EventReader<BallHitPlayer>,
+    speed: Res<BallSpeed>,
+    mut balls: Query<(&Transform, &mut Velocity), With<Ball>>,
+    players: Query<(&Transform, &BlockShape, &PlayerMotion), With<Player>>,
+) {
+    for hit in hits.read() {
+        let (Ok((ball_transform, mut velocity)), Ok((player_transform, shape, motion))) =
+            (balls.get_mut(hit.ball), players.get(hit.player))
+        else {
+            continue;
+        };
+
+        // Where the ball hit, in the frame of the paddle
+        let offset = (player_transform.rotation.inverse()
+            * (ball_transform.translation - player_transform.translation))
+            .truncate();
+        let along = (offset.x / (shape.size.x / 2.0)).clamp(-1.0, 1.0);
+        let angle = along * MAX_DEFLECTION_ANGLE;
+        // Away from whichever face of the paddle the ball hit
+        let away = if offset.y < 0.0 { -1.0 } else { 1.0 };
+        let direction = (player_transform.rotation
+            * Vec3::new(angle.sin(), away * angle.cos(), 0.0))
+        .truncate();
+
+        // Velocity of the paddle's surface where the ball hit, from its motion and spin
+        let contact = (player_transform.rotation * offset.extend(0.0)).truncate();
+        let surface_velocity = motion.velocity + motion.angular_velocity * contact.perp();
+
+        let outgoing = direction * speed.0 + surface_velocity * PADDLE_VELOCITY_TRANSFER;
+        let outgoing = outgoing.try_normalize().unwrap_or(direction);
+        velocity.linvel = with_min_vertical_speed(outgoing * speed.0);
+    }
+}
+
+fn track_last_hit_system(
+    mut hits: EventReader<BallHitPlayer>,
+    mut balls: Query<&mut LastHitBy, With<Ball>>,
+) {
+    for hit in hits.read() {
+        if let Ok(mut last_hit_by) = balls.get_mut(hit.ball) {
+            last_hit_by.0 = Some(hit.player);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.abs_diff_eq(b, 1e-3), "{a} is not {b}");
+    }
+
+    #[test]
+    fn steep_enough_velocity_is_kept() {
+        let velocity = Vec2::new(300.0, -400.0);
+        assert_eq!(with_min_vertical_speed(velocity), velocity);
+        assert_eq!(with_min_vertical_speed(Vec2::Y * 500.0), Vec2::Y * 500.0);
+    }
+
+    #[test]
+    fn shallow_velocity_is_steepened() {
+        let velocity = Vec2::new(-490.0, -10.0);
+        let steepened = with_min_vertical_speed(velocity);
+        assert!((steepened.length() - velocity.length()).abs() < 1e-3);
+        assert!((steepened.y + velocity.length() * MIN_VERTICAL_SPEED).abs() < 1e-3);
+        assert!(steepened.x < 0.0);
+    }
+
+    #[test]
+    fn horizontal_velocity_goes_up() {
+        let min_vertical = 500.0 * MIN_VERTICAL_SPEED;
+        let x = (500.0f32.powi(2) - min_vertical.powi(2)).sqrt();
+        assert_close(
+            with_min_vertical_speed(Vec2::X * 500.0),
+            Vec2::new(x, min_vertical),
+        );
+        assert_close(
+            with_min_vertical_speed(Vec2::NEG_X * 500.0),
+            Vec2::new(-x, min_vertical),
+        );
+    }
+
+    #[test]
+    fn zero_velocity_stays_zero() {
+        assert_eq!(with_min_vertical_speed(Vec2::ZERO), Vec2::ZERO);
+    }
+}
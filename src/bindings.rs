@@ -0,0 +1,623 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    overlay::CLICK_THROUGH_KEY,
+    player::{Action, LocalPlayers, Player, PlayerId, MAX_PLAYERS},
+    storage,
+};
+
+/// Keyboard bindings of the players: restored from disk on launch, editable at runtime
+/// from the controls panel and saved back whenever they change.
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        let file = BindingsFile(storage::config_dir().map(|dir| dir.join(BINDINGS_FILE)));
+        let bindings = file.load();
+
+        app.insert_resource(file)
+            .insert_resource(bindings)
+            .init_resource::<Rebinding>()
+            .add_systems(
+                Update,
+                (apply_key_bindings_system, save_key_bindings_system),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_controls_panel_system,
+                    rebind_button_system,
+                    capture_rebinding_system,
+                    update_controls_panel_system,
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub const BINDINGS_FILE: &str = "bindings.ron";
+
+/// Key opening and closing the controls panel.
+pub const CONTROLS_KEY: KeyCode = KeyCode::F1;
+
+/// Key bound to [`Action::PAUSE`] for every player, it also cancels a rebinding.
+pub const PAUSE_KEY: KeyCode = KeyCode::Escape;
+
+/// Keys the game handles itself, which no action can be bound to.
+pub const RESERVED_KEYS: [KeyCode; 3] = [PAUSE_KEY, CONTROLS_KEY, CLICK_THROUGH_KEY];
+
+/// Default keyboard scheme of each local player slot.
+const DEFAULT_KEYS: [[(Action, KeyCode); 6]; MAX_PLAYERS] = [
+    [
+        (Action::LEFT, KeyCode::KeyA),
+        (Action::RIGHT, KeyCode::KeyD),
+        (Action::UP, KeyCode::KeyW),
+        (Action::DOWN, KeyCode::KeyS),
+        (Action::RLEFT, KeyCode::KeyQ),
+        (Action::RRIGHT, KeyCode::KeyE),
+    ],
+    [
+        (Action::LEFT, KeyCode::ArrowLeft),
+        (Action::RIGHT, KeyCode::ArrowRight),
+        (Action::UP, KeyCode::ArrowUp),
+        (Action::DOWN, KeyCode::ArrowDown),
+        (Action::RLEFT, KeyCode::Comma),
+        (Action::RRIGHT, KeyCode::Period),
+    ],
+    [
+        (Action::LEFT, KeyCode::KeyJ),
+        (Action::RIGHT, KeyCode::KeyL),
+        (Action::UP, KeyCode::KeyI),
+        (Action::DOWN, KeyCode::KeyK),
+        (Action::RLEFT, KeyCode::KeyU),
+        (Action::RRIGHT, KeyCode::KeyO),
+    ],
+    [
+        (Action::LEFT, KeyCode::Numpad4),
+        (Action::RIGHT, KeyCode::Numpad6),
+        (Action::UP, KeyCode::Numpad8),
+        (Action::DOWN, KeyCode::Numpad5),
+        (Action::RLEFT, KeyCode::Numpad7),
+        (Action::RRIGHT, KeyCode::Numpad9),
+    ],
+];
+
+const DPAD_BUTTONS: [(Action, GamepadButtonType); 4] = [
+    (Action::LEFT, GamepadButtonType::DPadLeft),
+    (Action::RIGHT, GamepadButtonType::DPadRight),
+    (Action::UP, GamepadButtonType::DPadUp),
+    (Action::DOWN, GamepadButtonType::DPadDown),
+];
+
+/// The keys bound to each button action of every local player, and how gamepads are read.
+///
+/// Gamepads always move with the left stick or the d-pad and rotate with the right
+/// stick or the triggers. Each player uses the gamepad connected in the same position
+/// as their slot, if any.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    /// Keys of each player slot, indexed by [`PlayerId`].
+    pub players: Vec<BTreeMap<Action, Vec<KeyCode>>>,
+    #[serde(default)]
+    pub gamepad: GamepadSettings,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            players: DEFAULT_KEYS
+                .into_iter()
+                .map(|keys| {
+                    keys.into_iter()
+                        .map(|(action, key)| (action, vec![key]))
+                        .collect()
+                })
+                .collect(),
+            gamepad: GamepadSettings::default(),
+        }
+    }
+}
+
+/// Deadzones of the analog gamepad controls, as a fraction of their travel ignored
+/// around the rest position.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    pub stick_deadzone: f32,
+    pub trigger_deadzone: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            stick_deadzone: 0.15,
+            trigger_deadzone: 0.05,
+        }
+    }
+}
+
+/// A key that cannot be bound, because an action already uses it or the game does.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum BindingConflict {
+    #[error("{} is already bound to {action:?} of player {}", key_name(*key), player.0 + 1)]
+    Bound {
+        key: KeyCode,
+        player: PlayerId,
+        action: Action,
+    },
+    #[error("{} is reserved", key_name(*key))]
+    Reserved { key: KeyCode },
+}
+
+impl KeyBindings {
+    pub fn keys(&self, player: PlayerId, action: Action) -> &[KeyCode] {
+        self.players
+            .get(player.index())
+            .and_then(|keys| keys.get(&action))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The player and action `key` is bound to, if any.
+    pub fn action(&self, key: KeyCode) -> Option<(PlayerId, Action)> {
+        self.players
+            .iter()
+            .enumerate()
+            .find_map(|(player, actions)| {
+                actions
+                    .iter()
+                    .find(|(_, keys)| keys.contains(&key))
+                    .map(|(&action, _)| (PlayerId(player as u8), action))
+            })
+    }
+
+    /// Binds `action` of `player` to `key` alone, unless `key` is used by another action.
+    pub fn rebind(
+        &mut self,
+        player: PlayerId,
+        action: Action,
+        key: KeyCode,
+    ) -> Result<(), BindingConflict> {
+        if RESERVED_KEYS.contains(&key) {
+            return Err(BindingConflict::Reserved { key });
+        }
+        match self.action(key) {
+            Some((other_player, other_action))
+                if (other_player, other_action) != (player, action) =>
+            {
+                Err(BindingConflict::Bound {
+                    key,
+                    player: other_player,
+                    action: other_action,
+                })
+            }
+            _ => {
+                if let Some(actions) = self.players.get_mut(player.index()) {
+                    actions.insert(action, vec![key]);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks that no key is bound to two actions, of the same player or not, or to
+    /// one of the [`RESERVED_KEYS`].
+    pub fn validate(&self) -> Result<(), BindingConflict> {
+        let mut seen = BTreeMap::new();
+        for (player, actions) in self.players.iter().enumerate() {
+            for (&action, keys) in actions.iter() {
+                for &key in keys {
+                    if RESERVED_KEYS.contains(&key) {
+                        return Err(BindingConflict::Reserved { key });
+                    }
+                    if let Some((player, action)) =
+                        seen.insert(key, (PlayerId(player as u8), action))
+                    {
+                        return Err(BindingConflict::Bound {
+                            key,
+                            player,
+                            action,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The input map of `player`, reading `gamepad` if they have one.
+    pub fn input_map(&self, player: PlayerId, gamepad: Option<Gamepad>) -> InputMap<Action> {
+        let mut map = InputMap::default();
+        for (&action, keys) in self.players.get(player.index()).into_iter().flatten() {
+            for &key in keys {
+                map.insert(action, key);
+            }
+        }
+        map.insert(Action::PAUSE, PAUSE_KEY);
+
+        // Without a gamepad of their own, the player would read every connected gamepad
+        let Some(gamepad) = gamepad else {
+            return map;
+        };
+
+        // The deadzone processors panic outside of this range, the file may be hand edited
+        let stick_deadzone = self.gamepad.stick_deadzone.clamp(0.0, 0.95);
+        let trigger_deadzone = self.gamepad.trigger_deadzone.clamp(0.0, 0.95);
+        map.insert_multiple(DPAD_BUTTONS)
+            .insert(Action::PAUSE, GamepadButtonType::Start)
+            .insert_dual_axis(
+                Action::MOVE,
+                GamepadStick::LEFT.with_circle_deadzone(stick_deadzone),
+            )
+            .insert_axis(
+                Action::ROTATE,
+                GamepadControlAxis::RIGHT_X.with_deadzone_symmetric(stick_deadzone),
+            )
+            .insert_axis(
+                Action::ROTATE,
+                GamepadVirtualAxis::new(
+                    GamepadButtonType::LeftTrigger2,
+                    GamepadButtonType::RightTrigger2,
+                )
+                .with_deadzone_symmetric(trigger_deadzone),
+            )
+            .set_gamepad(gamepad);
+
+        map
+    }
+}
+
+/// Where the bindings are saved, `None` to keep them in memory only.
+#[derive(Resource, Clone, Debug)]
+pub struct BindingsFile(pub Option<PathBuf>);
+
+impl BindingsFile {
+    /// Reads the saved bindings, falling back to the defaults for actions the file
+    /// does not mention and entirely if it is missing, unreadable or conflicting.
+    ///
+    /// A file that cannot be parsed or has conflicting bindings is moved aside, rather
+    /// than overwritten by the defaults the next time the bindings are saved.
+    pub fn load(&self) -> KeyBindings {
+        let Some(path) = &self.0 else {
+            return KeyBindings::default();
+        };
+
+        let mut bindings = match storage::load::<KeyBindings>(path) {
+            Ok(Some(bindings)) => bindings,
+            Ok(None) => return KeyBindings::default(),
+            Err(error @ storage::StorageError::Parse { .. }) => {
+                back_up(path, &error);
+                return KeyBindings::default();
+            }
+            Err(error) => {
+                warn!("Using the default key bindings: {error}");
+                return KeyBindings::default();
+            }
+        };
+
+        let defaults = KeyBindings::default();
+        bindings.players.truncate(MAX_PLAYERS);
+        for (player, default_keys) in defaults.players.into_iter().enumerate() {
+            if player == bindings.players.len() {
+                bindings.players.push(BTreeMap::new());
+            }
+            for (action, keys) in default_keys {
+                bindings.players[player].entry(action).or_insert(keys);
+            }
+        }
+
+        if let Err(conflict) = bindings.validate() {
+            back_up(path, &conflict);
+            return KeyBindings::default();
+        }
+
+        bindings
+    }
+}
+
+/// Moves an unusable bindings file to a `.ron.bak` next to it.
+fn back_up(path: &Path, error: &dyn std::error::Error) {
+    let backup = path.with_extension("ron.bak");
+    warn!(
+        "Using the default key bindings, moving {} to {}: {error}",
+        path.display(),
+        backup.display()
+    );
+    if let Err(error) = std::fs::rename(path, &backup) {
+        warn!("Could not move {}: {error}", path.display());
+    }
+}
+
+/// The action waiting for a key press to be rebound, and the outcome of the last attempt.
+#[derive(Resource, Default)]
+pub struct Rebinding {
+    pub action: Option<(PlayerId, Action)>,
+    pub conflict: Option<BindingConflict>,
+}
+
+/// Human readable name of a key, `KeyA` becomes `A`.
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    ["Key", "Digit"]
+        .into_iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .map_or_else(|| name.clone(), str::to_string)
+}
+
+fn apply_key_bindings_system(
+    bindings: Res<KeyBindings>,
+    gamepads: Res<Gamepads>,
+    mut players: Query<(&PlayerId, &mut InputMap<Action>), With<Player>>,
+) {
+    // Hand out the gamepads in connection order, the n-th one to the n-th player
+    let mut connected: Vec<Gamepad> = gamepads.iter().collect();
+    connected.sort_by_key(|gamepad| gamepad.id);
+
+    for (&player, mut input_map) in players.iter_mut() {
+        if bindings.is_changed() || gamepads.is_changed() || input_map.is_added() {
+            let gamepad = connected.get(player.index()).copied();
+            *input_map = bindings.input_map(player, gamepad);
+        }
+    }
+}
+
+fn save_key_bindings_system(bindings: Res<KeyBindings>, file: Res<BindingsFile>) {
+    if !bindings.is_changed() || bindings.is_added() {
+        return;
+    }
+    let Some(path) = &file.0 else {
+        return;
+    };
+
+    if let Err(error) = storage::save(path, &*bindings) {
+        error!("Could not save the key bindings: {error}");
+    }
+}
+
+#[derive(Component)]
+struct ControlsPanel;
+
+#[derive(Component)]
+struct RebindButton(PlayerId, Action);
+
+#[derive(Component)]
+struct ControlsStatus;
+
+fn toggle_controls_panel_system(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    local_players: Res<LocalPlayers>,
+    panels: Query<Entity, With<ControlsPanel>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    if !keys.just_pressed(CONTROLS_KEY) {
+        return;
+    }
+
+    *rebinding = Rebinding::default();
+
+    if panels.is_empty() {
+        spawn_controls_panel(&mut commands, local_players.count());
+    } else {
+        for panel in panels.iter() {
+            commands.entity(panel).despawn_recursive();
+        }
+    }
+}
+
+fn spawn_controls_panel(commands: &mut Commands, players: usize) {
+    let text_style = TextStyle {
+        font_size: 20.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            ControlsPanel,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    align_self: AlignSelf::Center,
+                    justify_self: JustifySelf::Center,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(6.0),
+                    padding: UiRect::all(Val::Px(16.0)),
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.85).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+        ))
+        .with_children(|panel| {
+            panel.spawn(TextBundle::from_section(
+                format!(
+                    "Controls - click an action then press a key, {} to close",
+                    key_name(CONTROLS_KEY)
+                ),
+                text_style.clone(),
+            ));
+
+            // One column of actions per local player
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(16.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|columns| {
+                    for player in (0..players as u8).map(PlayerId) {
+                        columns
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    row_gap: Val::Px(6.0),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|column| {
+                                column.spawn(TextBundle::from_section(
+                                    format!("Player {}", player.0 + 1),
+                                    TextStyle {
+                                        color: player.color(),
+                                        ..text_style.clone()
+                                    },
+                                ));
+
+                                for action in Action::BUTTONS {
+                                    column
+                                        .spawn((
+                                            RebindButton(player, action),
+                                            ButtonBundle {
+                                                style: Style {
+                                                    padding: UiRect::axes(
+                                                        Val::Px(8.0),
+                                                        Val::Px(4.0),
+                                                    ),
+                                                    ..default()
+                                                },
+                                                background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+                                                ..default()
+                                            },
+                                        ))
+                                        .with_children(|button| {
+                                            button.spawn(TextBundle::from_section(
+                                                "",
+                                                text_style.clone(),
+                                            ));
+                                        });
+                                }
+                            });
+                    }
+                });
+
+            panel.spawn((
+                ControlsStatus,
+                TextBundle::from_section("", text_style.clone()),
+            ));
+        });
+}
+
+fn rebind_button_system(
+    buttons: Query<(&Interaction, &RebindButton), Changed<Interaction>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            rebinding.action = Some((button.0, button.1));
+            rebinding.conflict = None;
+        }
+    }
+}
+
+fn capture_rebinding_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<KeyBindings>,
+) {
+    let Some((player, action)) = rebinding.action else {
+        return;
+    };
+    let Some(&key) = keys.get_just_pressed().find(|&&key| key != CONTROLS_KEY) else {
+        return;
+    };
+
+    rebinding.action = None;
+    if key == PAUSE_KEY {
+        return;
+    }
+
+    // Only touch the bindings on success, so they are not saved for nothing
+    let mut updated = bindings.clone();
+    rebinding.conflict = updated.rebind(player, action, key).err();
+    if rebinding.conflict.is_none() && updated != *bindings {
+        *bindings = updated;
+    }
+}
+
+fn update_controls_panel_system(
+    bindings: Res<KeyBindings>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(&RebindButton, &Children)>,
+    added: Query<(), Added<ControlsPanel>>,
+    mut texts: Query<&mut Text, Without<ControlsStatus>>,
+    mut status: Query<&mut Text, With<ControlsStatus>>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (button, children) in buttons.iter() {
+        let label = if rebinding.action == Some((button.0, button.1)) {
+            "press a key...".to_string()
+        } else {
+            let keys: Vec<_> = bindings
+                .keys(button.0, button.1)
+                .iter()
+                .map(|&key| key_name(key))
+                .collect();
+            keys.join(", ")
+        };
+
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = format!("{:?}: {label}", button.1);
+        }
+    }
+
+    for mut text in status.iter_mut() {
+        text.sections[0].value = match (&rebinding.action, &rebinding.conflict) {
+            (Some(_), _) => format!("{} to cancel", key_name(PAUSE_KEY)),
+            (None, Some(conflict)) => conflict.to_string(),
+            (None, None) => String::new(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_keys_cannot_be_bound() {
+        for key in RESERVED_KEYS {
+            let mut bindings = KeyBindings::default();
+            assert_eq!(
+                bindings.rebind(PlayerId(0), Action::LEFT, key),
+                Err(BindingConflict::Reserved { key })
+            );
+
+            bindings.players[0].insert(Action::LEFT, vec![key]);
+            assert_eq!(bindings.validate(), Err(BindingConflict::Reserved { key }));
+        }
+    }
+
+    #[test]
+    fn corrupt_file_is_backed_up() {
+        let dir =
+            std::env::temp_dir().join(format!("blockbracker-bindings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(BINDINGS_FILE);
+        std::fs::write(&path, "not ron").unwrap();
+
+        assert_eq!(
+            BindingsFile(Some(path.clone())).load(),
+            KeyBindings::default()
+        );
+        assert!(!path.exists());
+        let backup = path.with_extension("ron.bak");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "not ron");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
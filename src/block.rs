@@ -0,0 +1,87 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{configure_game_sets, GameSet};
+
+/// Keeps the mesh and collider of blocks in sync with their [`BlockShape`].
+pub struct BlockPlugin;
+
+impl Plugin for BlockPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.add_systems(FixedUpdate, sync_block_shape_system.after(GameSet::Rules));
+    }
+}
+
+/// Size of a rectangular block, the single source its mesh, collider and bounds are derived from.
+///
+/// Changing it on a spawned entity rebuilds the mesh and collider to match.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BlockShape {
+    pub size: Vec2,
+}
+
+impl BlockShape {
+    pub fn new(size: Vec2) -> Self {
+        Self { size }
+    }
+
+    pub fn half_extents(&self) -> Vec2 {
+        self.size / 2.0
+    }
+
+    pub fn mesh(&self) -> Rectangle {
+        Rectangle::from_size(self.size)
+    }
+
+    pub fn collider(&self) -> Collider {
+        let half_extents = self.half_extents();
+        Collider::cuboid(half_extents.x, half_extents.y)
+    }
+}
+
+/// A rectangular block whose mesh and collider are both built from its [`BlockShape`].
+#[derive(Bundle)]
+pub struct BlockBundle {
+    pub shape: BlockShape,
+    pub mesh: MaterialMesh2dBundle<ColorMaterial>,
+    pub collider: Collider,
+}
+
+impl BlockBundle {
+    pub fn new(
+        shape: BlockShape,
+        material: Handle<ColorMaterial>,
+        transform: Transform,
+        meshes: &mut Assets<Mesh>,
+    ) -> Self {
+        Self {
+            shape,
+            mesh: MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(shape.mesh())),
+                material,
+                transform,
+                ..default()
+            },
+            collider: shape.collider(),
+        }
+    }
+}
+
+/// Rebuilds the mesh and collider of blocks whose [`BlockShape`] changed after spawning.
+fn sync_block_shape_system(
+    mut query: Query<(Ref<BlockShape>, &mut Collider, &mut Mesh2dHandle)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (shape, mut collider, mut mesh) in query.iter_mut() {
+        if !shape.is_changed() || shape.is_added() {
+            continue;
+        }
+
+        *collider = shape.collider();
+        mesh.0 = meshes.add(shape.mesh());
+    }
+}
@@ -0,0 +1,369 @@
+use std::str::FromStr;
+
+use bevy::{
+    prelude::*,
+    render::camera::{CameraUpdateSystem, ScalingMode, Viewport},
+    window::PrimaryWindow,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    arena::PlayField,
+    ball::BallLost,
+    collision::{BallHitBrick, PlayerHitObstacle},
+    obstacle::BrickDestroyed,
+    player::Player,
+    state::InLevel,
+    storage, GameState,
+};
+
+/// The camera looking at the play-field, which always shows the whole play-field and the
+/// same part of the game world whatever the size of the window, scaled to the window
+/// as set by the [`CameraScaling`].
+///
+/// The UI is scaled along with the play-field, so it keeps the same layout on top of it.
+/// On top of that the camera shakes on impacts, zooms in and out of the levels and can
+/// follow the players, each effect tuned by the player in the [`CameraSettings`].
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraScaling>()
+            .insert_resource(CameraSettings::load())
+            .add_systems(Startup, setup)
+            .add_systems(OnEnter(InLevel), zoom_in)
+            .add_systems(OnEnter(GameState::LevelComplete), zoom_out)
+            .add_systems(Update, (add_trauma_system, move_camera_system))
+            // Before the projection is updated from the window size
+            .add_systems(PostUpdate, fit_camera_system.before(CameraUpdateSystem));
+    }
+}
+
+/// Name of the [`CameraSettings`] file in the config directory.
+pub const CAMERA_FILE: &str = "camera.ron";
+
+/// Trauma every ball hitting a brick adds.
+const BRICK_HIT_TRAUMA: f32 = 0.1;
+/// Trauma every destroyed brick adds, chain explosions add up.
+const BRICK_DESTROYED_TRAUMA: f32 = 0.15;
+/// Trauma a lost ball adds.
+const BALL_LOST_TRAUMA: f32 = 0.5;
+/// Speed of a paddle running into an obstacle that adds full trauma, in pixels per second.
+const FULL_TRAUMA_HIT_VELOCITY: f32 = 1000.0;
+/// Trauma the shake loses every second.
+const TRAUMA_DECAY: f32 = 1.5;
+/// Offset and rotation of the camera at full trauma, in pixels and radians.
+const MAX_SHAKE_OFFSET: f32 = 24.0;
+const MAX_SHAKE_ANGLE: f32 = 0.05;
+/// How fast the shake changes direction.
+const SHAKE_FREQUENCY: f32 = 25.0;
+/// How fast the camera catches up with the players, the higher the tighter.
+const FOLLOW_STIFFNESS: f32 = 4.0;
+/// Zoom a level is entered from and left to, below 1 to show more than the play-field.
+const TRANSITION_ZOOM: f32 = 0.5;
+
+/// Camera effects, which can each be turned down or off for players sensitive to motion.
+///
+/// Read once on launch from [`CAMERA_FILE`], which the game never writes: a setting left
+/// out of the file keeps its default.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Strength of the screen shake on impacts, from 0 for none to 1 for full.
+    pub shake: f32,
+    /// Whether the camera zooms in on the players and follows them.
+    pub follow: bool,
+    /// How far the camera zooms in while following the players, above 1 to zoom in.
+    pub follow_zoom: f32,
+    /// Time the camera takes to zoom in when a level starts and out when it is complete,
+    /// 0 to switch at once.
+    pub zoom_duration: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            shake: 1.0,
+            follow: false,
+            follow_zoom: 1.25,
+            zoom_duration: 0.6,
+        }
+    }
+}
+
+impl CameraSettings {
+    fn load() -> Self {
+        storage::load_or_default(CAMERA_FILE).unwrap_or_else(|error| {
+            warn!("Using the default camera settings: {error}");
+            Self::default()
+        })
+    }
+
+    /// Zoom of the camera while a level is being played.
+    fn level_zoom(&self) -> f32 {
+        if self.follow {
+            self.follow_zoom.max(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// The camera showing the game world.
+#[derive(Component)]
+pub struct MainCamera;
+
+/// Where the camera looks before the shake is added, and how much it shakes.
+#[derive(Component, Clone, Debug)]
+pub struct CameraRig {
+    /// Center of the view, in the game world.
+    pub position: Vec2,
+    /// Current zoom, above 1 to zoom in.
+    pub zoom: f32,
+    pub transition: Option<ZoomTransition>,
+    /// From 0 for still to 1 for the strongest shake, it wears off over time.
+    pub trauma: f32,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+            transition: None,
+            trauma: 0.0,
+        }
+    }
+}
+
+impl CameraRig {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    /// Zooms smoothly from `from` to `to` over `duration` seconds.
+    pub fn zoom_between(&mut self, from: f32, to: f32, duration: f32) {
+        if duration <= 0.0 {
+            self.zoom = to;
+            self.transition = None;
+            return;
+        }
+
+        self.zoom = from;
+        self.transition = Some(ZoomTransition {
+            from,
+            to,
+            duration,
+            elapsed: 0.0,
+        });
+    }
+}
+
+/// A zoom in progress, eased in and out.
+#[derive(Clone, Copy, Debug)]
+pub struct ZoomTransition {
+    pub from: f32,
+    pub to: f32,
+    /// In seconds.
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+/// How the play-field is scaled to the window.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraScaling {
+    /// As large as the window allows without changing its aspect ratio, with bars of the
+    /// clear color on the sides the play-field does not reach.
+    #[default]
+    Letterbox,
+    /// As large as the window allows without changing its aspect ratio, showing more of
+    /// the game world around it on the sides the play-field does not reach.
+    Fit,
+    /// Stretched over the whole window, distorted when the aspect ratios differ.
+    Stretch,
+    /// Scaled by the largest whole number the window allows so every pixel of the game
+    /// world covers as many pixels of the screen, letterboxed. In a window smaller than
+    /// the play-field, it is shrunk like [`CameraScaling::Letterbox`].
+    Integer,
+}
+
+#[derive(Debug, Error)]
+#[error("unknown camera scaling \"{0}\", expected letterbox, fit, stretch or integer")]
+pub struct UnknownCameraScaling(String);
+
+impl FromStr for CameraScaling {
+    type Err = UnknownCameraScaling;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "letterbox" => Ok(Self::Letterbox),
+            "fit" => Ok(Self::Fit),
+            "stretch" => Ok(Self::Stretch),
+            "integer" => Ok(Self::Integer),
+            _ => Err(UnknownCameraScaling(name.to_string())),
+        }
+    }
+}
+
+fn setup(mut commands: Commands) {
+    // Add a 2D camera
+    commands.spawn((Camera2dBundle::default(), MainCamera, CameraRig::default()));
+}
+
+fn zoom_in(settings: Res<CameraSettings>, mut rigs: Query<&mut CameraRig>) {
+    for mut rig in rigs.iter_mut() {
+        rig.position = Vec2::ZERO;
+        rig.trauma = 0.0;
+        rig.zoom_between(
+            TRANSITION_ZOOM,
+            settings.level_zoom(),
+            settings.zoom_duration,
+        );
+    }
+}
+
+fn zoom_out(settings: Res<CameraSettings>, mut rigs: Query<&mut CameraRig>) {
+    for mut rig in rigs.iter_mut() {
+        let zoom = rig.zoom;
+        rig.zoom_between(zoom, TRANSITION_ZOOM, settings.zoom_duration);
+    }
+}
+
+fn add_trauma_system(
+    mut brick_hits: EventReader<BallHitBrick>,
+    mut destroyed: EventReader<BrickDestroyed>,
+    mut lost: EventReader<BallLost>,
+    mut obstacle_hits: EventReader<PlayerHitObstacle>,
+    mut rigs: Query<&mut CameraRig>,
+) {
+    let trauma = brick_hits.read().count() as f32 * BRICK_HIT_TRAUMA
+        + destroyed.read().count() as f32 * BRICK_DESTROYED_TRAUMA
+        + lost.read().count() as f32 * BALL_LOST_TRAUMA
+        + obstacle_hits
+            .read()
+            .map(|hit| hit.hit_velocity.length() / FULL_TRAUMA_HIT_VELOCITY)
+            .sum::<f32>();
+
+    if trauma > 0.0 {
+        for mut rig in rigs.iter_mut() {
+            rig.add_trauma(trauma);
+        }
+    }
+}
+
+/// Moves the camera after the players and through its zoom transition, then shakes it.
+///
+/// Runs on real time, so the camera settles down while the game is paused.
+fn move_camera_system(
+    time: Res<Time<Real>>,
+    settings: Res<CameraSettings>,
+    play_field: Res<PlayField>,
+    players: Query<&Transform, (With<Player>, Without<CameraRig>)>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection, &mut CameraRig)>,
+) {
+    let dt = time.delta_seconds();
+
+    for (mut transform, mut projection, mut rig) in cameras.iter_mut() {
+        if let Some(mut transition) = rig.transition {
+            transition.elapsed += dt;
+            let progress = (transition.elapsed / transition.duration).min(1.0);
+            let eased = progress * progress * (3.0 - 2.0 * progress);
+            rig.zoom = transition.from + (transition.to - transition.from) * eased;
+            rig.transition = (progress < 1.0).then_some(transition);
+        }
+
+        // Towards the middle of the players, without showing past the edges of the
+        // play-field once zoomed in
+        let mut target = Vec2::ZERO;
+        if settings.follow && !players.is_empty() {
+            let sum: Vec2 = players
+                .iter()
+                .map(|player| player.translation.truncate())
+                .sum();
+            let bounds = (play_field.half_size() * (1.0 - 1.0 / rig.zoom)).max(Vec2::ZERO);
+            target = (sum / players.iter().len() as f32).clamp(-bounds, bounds);
+        }
+        rig.position = rig
+            .position
+            .lerp(target, 1.0 - (-FOLLOW_STIFFNESS * dt).exp());
+
+        // Shake grows with the square of the trauma, so small hits stay subtle
+        rig.trauma = (rig.trauma - TRAUMA_DECAY * dt).max(0.0);
+        let shake = rig.trauma * rig.trauma * settings.shake.clamp(0.0, 1.0);
+        let t = time.elapsed_seconds() * SHAKE_FREQUENCY;
+        let offset = Vec2::new(noise(t, 0.0), noise(t, 10.0)) * MAX_SHAKE_OFFSET * shake;
+        let angle = noise(t, 20.0) * MAX_SHAKE_ANGLE * shake;
+
+        transform.translation = (rig.position + offset).extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(angle);
+        projection.scale = 1.0 / rig.zoom.max(f32::EPSILON);
+    }
+}
+
+/// Smooth noise in `-1.0..=1.0`, different for every `seed`.
+fn noise(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() + 0.5 * (2.3 * t + 1.7 * seed).sin() + 0.25 * (5.1 * t + 3.1 * seed).sin())
+        / 1.75
+}
+
+/// Sets the viewport and projection of the camera from the size of the window.
+fn fit_camera_system(
+    scaling: Res<CameraScaling>,
+    play_field: Res<PlayField>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &mut OrthographicProjection), With<MainCamera>>,
+    mut ui_scale: ResMut<UiScale>,
+    mut fitted: Local<Option<(CameraScaling, Vec2, UVec2)>>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let window_size = window.physical_size();
+    // A minimized window has nothing to fit in, and nothing changed since the last fit
+    if window_size.min_element() == 0 || *fitted == Some((*scaling, play_field.size, window_size)) {
+        return;
+    }
+    *fitted = Some((*scaling, play_field.size, window_size));
+
+    // Screen pixels per pixel of the game world when the play-field fills the window
+    // along one axis
+    let fit = (window_size.as_vec2() / play_field.size).min_element();
+    let scale = match *scaling {
+        CameraScaling::Integer if fit >= 1.0 => fit.floor(),
+        _ => fit,
+    };
+
+    let viewport = match *scaling {
+        CameraScaling::Letterbox | CameraScaling::Integer => {
+            let size = (play_field.size * scale)
+                .round()
+                .as_uvec2()
+                .min(window_size);
+            Some(Viewport {
+                physical_position: (window_size - size) / 2,
+                physical_size: size,
+                ..default()
+            })
+        }
+        CameraScaling::Fit | CameraScaling::Stretch => None,
+    };
+    let scaling_mode = match *scaling {
+        CameraScaling::Fit => ScalingMode::AutoMin {
+            min_width: play_field.size.x,
+            min_height: play_field.size.y,
+        },
+        _ => ScalingMode::Fixed {
+            width: play_field.size.x,
+            height: play_field.size.y,
+        },
+    };
+
+    for (mut camera, mut projection) in cameras.iter_mut() {
+        camera.viewport.clone_from(&viewport);
+        projection.scaling_mode = scaling_mode;
+    }
+
+    // The UI is laid out in logical pixels
+    ui_scale.0 = scale / window.scale_factor();
+}
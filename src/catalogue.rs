@@ -0,0 +1,124 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// The brick catalogue every level picks its bricks from.
+pub const BRICK_CATALOGUE: &str = "bricks.catalogue.ron";
+
+/// A kind of brick a level can reference by name.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BrickType {
+    /// sRGB color of the brick.
+    pub color: (f32, f32, f32),
+    /// Hits from balls and laser shots the brick takes before it is destroyed, the brick
+    /// darkens with every hit.
+    #[serde(default = "default_hit_points")]
+    pub hit_points: u32,
+    /// Never destroyed, and not needed to clear the level.
+    #[serde(default)]
+    pub indestructible: bool,
+    /// Distance within which the bricks around take a hit when this one is destroyed.
+    #[serde(default)]
+    pub explosion_radius: Option<f32>,
+    /// Path the brick moves along, in a loop.
+    #[serde(default)]
+    pub path: Option<PathType>,
+}
+
+fn default_hit_points() -> u32 {
+    1
+}
+
+/// Path of a moving brick: from its cell through every waypoint and back.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PathType {
+    /// Offsets from the brick's cell in the level layout.
+    pub waypoints: Vec<(f32, f32)>,
+    /// Speed along the path, in pixels per second.
+    pub speed: f32,
+}
+
+/// The kinds of bricks, by name, described by a `.catalogue.ron` asset.
+#[derive(Asset, TypePath, Clone, Debug, Default, Deserialize)]
+pub struct BrickCatalogue {
+    pub bricks: HashMap<String, BrickType>,
+}
+
+#[derive(Debug, Error)]
+pub enum CatalogueValidationError {
+    #[error("brick \"{0}\" must have at least one hit point")]
+    ZeroHitPoints(String),
+    #[error("brick \"{0}\" must have a positive explosion radius")]
+    InvalidExplosionRadius(String),
+    #[error("brick \"{0}\" must have a path with waypoints and a positive speed")]
+    InvalidPath(String),
+}
+
+impl BrickCatalogue {
+    pub fn validate(&self) -> Result<(), CatalogueValidationError> {
+        for (name, brick_type) in self.bricks.iter() {
+            if brick_type.hit_points == 0 && !brick_type.indestructible {
+                return Err(CatalogueValidationError::ZeroHitPoints(name.clone()));
+            }
+            if brick_type
+                .explosion_radius
+                .is_some_and(|radius| radius <= 0.0)
+            {
+                return Err(CatalogueValidationError::InvalidExplosionRadius(
+                    name.clone(),
+                ));
+            }
+            if brick_type
+                .path
+                .as_ref()
+                .is_some_and(|path| path.waypoints.is_empty() || path.speed <= 0.0)
+            {
+                return Err(CatalogueValidationError::InvalidPath(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CatalogueLoaderError {
+    #[error("could not read brick catalogue: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse brick catalogue: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid brick catalogue: {0}")]
+    Invalid(#[from] CatalogueValidationError),
+}
+
+#[derive(Default)]
+pub struct CatalogueLoader;
+
+impl AssetLoader for CatalogueLoader {
+    type Asset = BrickCatalogue;
+    type Settings = ();
+    type Error = CatalogueLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<BrickCatalogue, CatalogueLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let catalogue: BrickCatalogue = ron::de::from_bytes(&bytes)?;
+        catalogue.validate()?;
+
+        Ok(catalogue)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["catalogue.ron"]
+    }
+}
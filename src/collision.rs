@@ -0,0 +1,203 @@
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    arena::KillZone,
+    ball::{Ball, BallLost},
+    configure_game_sets,
+    movement::PlayerMotion,
+    obstacle::Obstacle,
+    player::Player,
+    powerup::{Laser, PowerUp},
+    GameSet,
+};
+
+/// Translates the contacts of every physics step into typed gameplay events, so the
+/// systems reacting to them do not have to sort out raw Rapier events.
+///
+/// Contacts of the balls come from Rapier's [`CollisionEvent`]s, those of the paddles
+/// from their character controllers: a kinematic paddle is stopped by the obstacles
+/// before ever touching them, so Rapier never reports these contacts.
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.add_event::<BallHitPlayer>()
+            .add_event::<BallHitBrick>()
+            .add_event::<PlayerHitObstacle>()
+            .add_event::<PlayerCollectedPowerUp>()
+            .add_event::<LaserHitBrick>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    ball_contacts_system,
+                    player_contacts_system,
+                    sensor_contacts_system,
+                )
+                    .in_set(GameSet::Contacts),
+            );
+    }
+}
+
+/// Sent when a ball starts touching a paddle.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BallHitPlayer {
+    pub ball: Entity,
+    pub player: Entity,
+}
+
+/// Sent when a ball starts touching a brick.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BallHitBrick {
+    pub ball: Entity,
+    pub brick: Entity,
+}
+
+/// Sent when a paddle runs into an obstacle.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerHitObstacle {
+    pub player: Entity,
+    pub obstacle: Entity,
+    /// Part of the paddle's velocity going into the obstacle, in pixels per second. Its
+    /// length tells how hard the hit was.
+    pub hit_velocity: Vec2,
+}
+
+/// Sent when a paddle catches a falling power-up.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerCollectedPowerUp {
+    pub player: Entity,
+    pub power_up: Entity,
+}
+
+/// Sent when a laser shot reaches a brick.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LaserHitBrick {
+    pub laser: Entity,
+    pub brick: Entity,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ball_contacts_system(
+    mut collision_events: EventReader<CollisionEvent>,
+    balls: Query<(), With<Ball>>,
+    players: Query<(), With<Player>>,
+    bricks: Query<(), With<Obstacle>>,
+    kill_zones: Query<(), With<KillZone>>,
+    mut ball_hit_player: EventWriter<BallHitPlayer>,
+    mut ball_hit_brick: EventWriter<BallHitBrick>,
+    mut lost: EventWriter<BallLost>,
+) {
+    for event in collision_events.read() {
+        let CollisionEvent::Started(first, second, _) = *event else {
+            continue;
+        };
+
+        for (ball, other) in [(first, second), (second, first)] {
+            if !balls.contains(ball) {
+                continue;
+            }
+
+            if players.contains(other) {
+                ball_hit_player.send(BallHitPlayer {
+                    ball,
+                    player: other,
+                });
+            } else if bricks.contains(other) {
+                ball_hit_brick.send(BallHitBrick { ball, brick: other });
+            } else if kill_zones.contains(other) {
+                lost.send(BallLost { ball });
+            }
+        }
+    }
+}
+
+/// Sends a [`PlayerHitObstacle`] when a paddle's controller is stopped by an obstacle it
+/// was not already touching on the previous tick, so pushing against one does not
+/// repeat the event every tick.
+fn player_contacts_system(
+    players: Query<
+        (
+            Entity,
+            Ref<KinematicCharacterControllerOutput>,
+            &PlayerMotion,
+        ),
+        With<Player>,
+    >,
+    obstacles: Query<(), With<Obstacle>>,
+    mut touching: Local<EntityHashMap<Vec<Entity>>>,
+    mut player_hit_obstacle: EventWriter<PlayerHitObstacle>,
+) {
+    let previously_touching = std::mem::take(&mut *touching);
+
+    for (player, output, motion) in players.iter() {
+        // The output is only updated on the ticks the controller moved the paddle
+        if !output.is_changed() {
+            if let Some(obstacles) = previously_touching.get(&player) {
+                touching.insert(player, obstacles.clone());
+            }
+            continue;
+        }
+
+        let mut contacts = Vec::new();
+        for collision in &output.collisions {
+            let obstacle = collision.entity;
+            if !obstacles.contains(obstacle) || contacts.contains(&obstacle) {
+                continue;
+            }
+            contacts.push(obstacle);
+
+            let already_touching = previously_touching
+                .get(&player)
+                .is_some_and(|obstacles| obstacles.contains(&obstacle));
+            if already_touching {
+                continue;
+            }
+
+            // The hit normal points out of the obstacle, towards the paddle
+            let hit_velocity = collision.hit.details.map_or(Vec2::ZERO, |details| {
+                let into_obstacle = -details.normal1;
+                into_obstacle * motion.velocity.dot(into_obstacle).max(0.0)
+            });
+            player_hit_obstacle.send(PlayerHitObstacle {
+                player,
+                obstacle,
+                hit_velocity,
+            });
+        }
+        touching.insert(player, contacts);
+    }
+}
+
+/// Contacts of the power-ups and laser shots, sensors which only report that they
+/// started overlapping something.
+fn sensor_contacts_system(
+    mut collision_events: EventReader<CollisionEvent>,
+    power_ups: Query<(), With<PowerUp>>,
+    lasers: Query<(), With<Laser>>,
+    players: Query<(), With<Player>>,
+    bricks: Query<(), With<Obstacle>>,
+    mut collected: EventWriter<PlayerCollectedPowerUp>,
+    mut laser_hit_brick: EventWriter<LaserHitBrick>,
+) {
+    for event in collision_events.read() {
+        let CollisionEvent::Started(first, second, _) = *event else {
+            continue;
+        };
+
+        for (sensor, other) in [(first, second), (second, first)] {
+            if power_ups.contains(sensor) && players.contains(other) {
+                collected.send(PlayerCollectedPowerUp {
+                    player: other,
+                    power_up: sensor,
+                });
+            } else if lasers.contains(sensor) && bricks.contains(other) {
+                laser_hit_brick.send(LaserHitBrick {
+                    laser: sensor,
+                    brick: other,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    player::Action,
+    simulation::{RenderInterpolation, TickRate},
+    BindingsPlugin, CameraPlugin, GamePlugins, GameState, HighScorePlugin, MenuPlugin,
+    OverlayPlugin,
+};
+
+/// Time simulated by every update of a headless app.
+pub const HEADLESS_TIMESTEP: Duration = Duration::from_micros(16_667);
+
+/// Builds an app simulating the game without a window, renderer or input devices.
+///
+/// The app skips the menus and starts the current level as soon as it is loaded, and
+/// exits once that level is over, won or lost.
+/// Every call to [`App::update`] advances time by exactly `timestep`, which is also the
+/// duration of a simulation tick, so every update runs one tick and runs are
+/// reproducible. Player input is not read from any device: drive it by pressing
+/// actions on the players' [`ActionState<Action>`] directly, they stay pressed until
+/// released.
+pub fn headless_app(timestep: Duration) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        // Keep runs reproducible, an asset edited on disk must not change them midway
+        AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..default()
+        },
+        TransformPlugin,
+        HierarchyPlugin,
+        StatesPlugin,
+    ))
+    // Registered by the render plugins in the windowed game, needed to spawn level entities
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+    // In server mode the action states are ticked but never overwritten from devices
+    .add_plugins(InputManagerPlugin::<Action>::server())
+    // Without input devices there is nothing to bind, no menu to click through and no
+    // one to type initials, which also keeps simulated runs out of the high scores.
+    // Without a window there is nothing to look at either.
+    .add_plugins(
+        GamePlugins
+            .build()
+            .disable::<CameraPlugin>()
+            .disable::<BindingsPlugin>()
+            .disable::<MenuPlugin>()
+            .disable::<HighScorePlugin>()
+            .disable::<OverlayPlugin>(),
+    )
+    .insert_resource(TickRate::from_timestep(timestep))
+    // Nothing is rendered, the transforms are those of the last tick
+    .insert_resource(RenderInterpolation(false))
+    // Straight into the level
+    .insert_resource(NextState::Pending(GameState::Loading))
+    // Nothing to go back to without the menus
+    .add_systems(OnEnter(GameState::GameOver), exit)
+    .add_systems(OnEnter(GameState::LevelComplete), exit);
+
+    app
+}
+
+fn exit(mut exits: EventWriter<AppExit>) {
+    exits.send(AppExit::Success);
+}
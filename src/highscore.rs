@@ -0,0 +1,503 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bindings::key_name,
+    level::{CurrentLevel, Level},
+    score::{PlayTime, Score},
+    state::GameState,
+    storage,
+};
+
+/// Best scores of every level: restored from disk on launch, and added to when a game
+/// ends with a score good enough to enter the table, once the players typed their
+/// initials.
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        let file = HighScoresFile(storage::config_dir().map(|dir| dir.join(HIGH_SCORES_FILE)));
+        let high_scores = file.load();
+
+        app.insert_resource(file)
+            .insert_resource(high_scores)
+            .init_resource::<LatestHighScore>()
+            .add_systems(OnEnter(GameState::GameOver), start_initials_entry)
+            .add_systems(OnEnter(GameState::LevelComplete), start_initials_entry)
+            .add_systems(
+                Update,
+                (initials_entry_system, update_initials_panel_system)
+                    .chain()
+                    .distributive_run_if(resource_exists::<InitialsEntry>),
+            );
+    }
+}
+
+pub const HIGH_SCORES_FILE: &str = "highscores.ron";
+
+/// Number of scores kept per level.
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const MAX_INITIALS: usize = 3;
+
+/// A score in the table of a level.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    /// Initials of the players.
+    pub name: String,
+    pub score: u32,
+    /// Time it took to get the score.
+    pub time: Duration,
+    /// Day the score was set, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+impl HighScoreEntry {
+    /// Whether `score` set in `time` ranks above this entry: a higher score, or the same
+    /// one sooner.
+    fn beaten_by(&self, score: u32, time: Duration) -> bool {
+        score > self.score || (score == self.score && time < self.time)
+    }
+}
+
+/// The best scores of every level, keyed by level name, best first.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScores {
+    pub levels: BTreeMap<String, Vec<HighScoreEntry>>,
+}
+
+impl HighScores {
+    pub fn entries(&self, level: &str) -> &[HighScoreEntry] {
+        self.levels.get(level).map_or(&[], Vec::as_slice)
+    }
+
+    /// Rank `score` would get in the table of `level`, `None` if it does not make it.
+    ///
+    /// Ties rank below the scores already in the table.
+    pub fn rank(&self, level: &str, score: u32, time: Duration) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+
+        let entries = self.entries(level);
+        let rank = entries
+            .iter()
+            .position(|entry| entry.beaten_by(score, time))
+            .unwrap_or(entries.len());
+        (rank < MAX_HIGH_SCORES).then_some(rank)
+    }
+
+    /// Adds `entry` to the table of `level`, dropping the scores pushed out of it.
+    ///
+    /// Returns the rank of the entry, `None` if it did not make it.
+    pub fn insert(&mut self, level: &str, entry: HighScoreEntry) -> Option<usize> {
+        let rank = self.rank(level, entry.score, entry.time)?;
+        let entries = self.levels.entry(level.to_string()).or_default();
+        entries.insert(rank, entry);
+        entries.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    /// Puts the tables back in order and within size, in case the file was edited.
+    fn normalize(&mut self) {
+        self.levels.retain(|_, entries| !entries.is_empty());
+        for entries in self.levels.values_mut() {
+            // Stable, so tied entries keep the order they were set in
+            entries.sort_by(|a, b| {
+                if b.beaten_by(a.score, a.time) {
+                    std::cmp::Ordering::Less
+                } else if a.beaten_by(b.score, b.time) {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            });
+            entries.truncate(MAX_HIGH_SCORES);
+        }
+    }
+}
+
+/// Where the high scores are saved, `None` to keep them in memory only.
+#[derive(Resource, Clone, Debug)]
+pub struct HighScoresFile(pub Option<PathBuf>);
+
+impl HighScoresFile {
+    /// Reads the saved high scores, starting from empty tables if the file is missing or
+    /// unreadable.
+    ///
+    /// A file that cannot be parsed is moved aside rather than overwritten by the next
+    /// save, so the scores in it can still be recovered by hand.
+    pub fn load(&self) -> HighScores {
+        let Some(path) = &self.0 else {
+            return HighScores::default();
+        };
+
+        match storage::load::<HighScores>(path) {
+            Ok(Some(mut high_scores)) => {
+                high_scores.normalize();
+                high_scores
+            }
+            Ok(None) => HighScores::default(),
+            Err(error @ storage::StorageError::Parse { .. }) => {
+                let backup = path.with_extension("ron.bak");
+                warn!(
+                    "Starting new high score tables, moving the old ones to {}: {error}",
+                    backup.display()
+                );
+                if let Err(error) = std::fs::rename(path, &backup) {
+                    warn!("Could not move {}: {error}", path.display());
+                }
+                HighScores::default()
+            }
+            Err(error) => {
+                warn!("Starting new high score tables: {error}");
+                HighScores::default()
+            }
+        }
+    }
+
+    pub fn save(&self, high_scores: &HighScores) {
+        let Some(path) = &self.0 else {
+            return;
+        };
+
+        if let Err(error) = storage::save(path, high_scores) {
+            error!("Could not save the high scores: {error}");
+        }
+    }
+}
+
+/// The last score entered in a table, to highlight it on the leaderboard.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LatestHighScore(pub Option<(String, usize)>);
+
+/// A score that made it to the table of its level, waiting for the players' initials.
+///
+/// The menus ignore the confirm keys while it exists.
+#[derive(Resource, Clone, Debug)]
+pub struct InitialsEntry {
+    pub level: String,
+    pub score: u32,
+    pub time: Duration,
+    pub initials: String,
+}
+
+/// Formats `time` as minutes and seconds, `1:05`.
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() / 86_400) as i64;
+
+    // Days since the epoch to a civil date, from Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Letters the initials can be made of, cycled through with a gamepad.
+const INITIALS_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+#[derive(Component)]
+struct InitialsPanel;
+
+#[derive(Component)]
+struct InitialsText;
+
+fn start_initials_entry(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    score: Res<Score>,
+    play_time: Res<PlayTime>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    high_scores: Res<HighScores>,
+) {
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    if high_scores
+        .rank(&level.name, score.points, play_time.0)
+        .is_none()
+    {
+        return;
+    }
+
+    commands.insert_resource(InitialsEntry {
+        level: level.name.clone(),
+        score: score.points,
+        time: play_time.0,
+        initials: String::new(),
+    });
+    spawn_initials_panel(&mut commands, *state.get(), score.points);
+}
+
+fn spawn_initials_panel(commands: &mut Commands, state: GameState, score: u32) {
+    let text_style = TextStyle {
+        font_size: 24.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            InitialsPanel,
+            StateScoped(state),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(10.0),
+                    justify_self: JustifySelf::Center,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.0),
+                    padding: UiRect::all(Val::Px(16.0)),
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.85).into(),
+                z_index: ZIndex::Global(6),
+                ..default()
+            },
+        ))
+        .with_children(|panel| {
+            panel.spawn(TextBundle::from_section(
+                format!("New high score! {score}"),
+                TextStyle {
+                    font_size: 36.0,
+                    color: Color::srgb(1.0, 0.8, 0.2),
+                    ..default()
+                },
+            ));
+            panel.spawn((
+                InitialsText,
+                TextBundle::from_section("", text_style.clone()),
+            ));
+            panel.spawn(TextBundle::from_section(
+                "Enter to save, Escape to skip",
+                TextStyle {
+                    font_size: 18.0,
+                    ..text_style
+                },
+            ));
+        });
+}
+
+/// Types the initials from the keyboard, or from the D-pad of a gamepad: up and down
+/// change the last letter, right adds one and left removes it.
+#[allow(clippy::too_many_arguments)]
+fn initials_entry_system(
+    mut commands: Commands,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut gamepad_buttons: ResMut<ButtonInput<GamepadButton>>,
+    mut entry: ResMut<InitialsEntry>,
+    mut high_scores: ResMut<HighScores>,
+    file: Res<HighScoresFile>,
+    mut latest: ResMut<LatestHighScore>,
+    panels: Query<Entity, With<InitialsPanel>>,
+) {
+    let gamepad_pressed = |button_type| {
+        gamepad_buttons
+            .get_just_pressed()
+            .any(|button| button.button_type == button_type)
+    };
+
+    for &key in keys.get_just_pressed() {
+        let name = key_name(key);
+        if name.len() == 1 && name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            if entry.initials.len() < MAX_INITIALS {
+                entry.initials.push_str(&name);
+            }
+        } else if key == KeyCode::Backspace {
+            entry.initials.pop();
+        }
+    }
+
+    if gamepad_pressed(GamepadButtonType::DPadRight) && entry.initials.len() < MAX_INITIALS {
+        entry.initials.push('A');
+    }
+    if gamepad_pressed(GamepadButtonType::DPadLeft) {
+        entry.initials.pop();
+    }
+    for (button_type, step) in [
+        (GamepadButtonType::DPadUp, 1),
+        (GamepadButtonType::DPadDown, INITIALS_ALPHABET.len() - 1),
+    ] {
+        if !gamepad_pressed(button_type) {
+            continue;
+        }
+        let last = entry.initials.pop().map_or(0, |letter| {
+            INITIALS_ALPHABET
+                .iter()
+                .position(|&c| c == letter as u8)
+                .map_or(0, |index| (index + step) % INITIALS_ALPHABET.len())
+        });
+        entry.initials.push(INITIALS_ALPHABET[last] as char);
+    }
+
+    let skipped = keys.just_pressed(KeyCode::Escape) || gamepad_pressed(GamepadButtonType::East);
+    let confirmed = !entry.initials.is_empty()
+        && (keys.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter])
+            || gamepad_pressed(GamepadButtonType::South));
+    if !skipped && !confirmed {
+        return;
+    }
+
+    if confirmed {
+        let rank = high_scores.insert(
+            &entry.level,
+            HighScoreEntry {
+                name: entry.initials.clone(),
+                score: entry.score,
+                time: entry.time,
+                date: today(),
+            },
+        );
+        latest.0 = rank.map(|rank| (entry.level.clone(), rank));
+        file.save(&high_scores);
+    }
+
+    // The key that closed the entry must not also press a menu button this frame
+    keys.clear();
+    gamepad_buttons.clear();
+    commands.remove_resource::<InitialsEntry>();
+    for panel in panels.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+}
+
+fn update_initials_panel_system(
+    entry: Res<InitialsEntry>,
+    mut texts: Query<&mut Text, With<InitialsText>>,
+) {
+    if !entry.is_changed() {
+        return;
+    }
+
+    let blanks = "_".repeat(MAX_INITIALS.saturating_sub(entry.initials.len()));
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = format!("Your initials: {}{blanks}", entry.initials);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32, secs: u64) -> HighScoreEntry {
+        HighScoreEntry {
+            name: name.to_string(),
+            score,
+            time: Duration::from_secs(secs),
+            date: "2024-01-01".to_string(),
+        }
+    }
+
+    #[test]
+    fn ties_rank_by_time_then_below_existing_scores() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert("level", entry("AAA", 500, 60));
+        high_scores.insert("level", entry("BBB", 300, 60));
+
+        // Same score, sooner: above
+        assert_eq!(
+            high_scores.rank("level", 500, Duration::from_secs(30)),
+            Some(0)
+        );
+        // Same score, same time: below the one already there
+        assert_eq!(
+            high_scores.rank("level", 500, Duration::from_secs(60)),
+            Some(1)
+        );
+        // Same score, later: below
+        assert_eq!(
+            high_scores.rank("level", 500, Duration::from_secs(90)),
+            Some(1)
+        );
+        assert_eq!(high_scores.rank("level", 0, Duration::ZERO), None);
+
+        assert_eq!(high_scores.insert("level", entry("CCC", 500, 60)), Some(1));
+        let names: Vec<_> = high_scores
+            .entries("level")
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["AAA", "CCC", "BBB"]);
+    }
+
+    #[test]
+    fn ties_do_not_enter_a_full_table() {
+        let mut high_scores = HighScores::default();
+        for _ in 0..MAX_HIGH_SCORES {
+            high_scores.insert("level", entry("AAA", 100, 60));
+        }
+        assert_eq!(
+            high_scores.rank("level", 100, Duration::from_secs(60)),
+            None
+        );
+        assert_eq!(
+            high_scores.rank("level", 100, Duration::from_secs(59)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn normalize_keeps_tied_entries_in_order() {
+        let mut high_scores = HighScores::default();
+        high_scores.levels.insert(
+            "level".to_string(),
+            vec![
+                entry("AAA", 100, 60),
+                entry("BBB", 200, 60),
+                entry("CCC", 100, 60),
+                entry("DDD", 100, 30),
+            ],
+        );
+        high_scores.normalize();
+        let names: Vec<_> = high_scores
+            .entries("level")
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["BBB", "DDD", "AAA", "CCC"]);
+    }
+
+    #[test]
+    fn corrupt_file_is_backed_up() {
+        let dir =
+            std::env::temp_dir().join(format!("blockbracker-highscores-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(HIGH_SCORES_FILE);
+        std::fs::write(&path, "not ron").unwrap();
+
+        assert_eq!(
+            HighScoresFile(Some(path.clone())).load(),
+            HighScores::default()
+        );
+        assert!(!path.exists());
+        let backup = path.with_extension("ron.bak");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "not ron");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
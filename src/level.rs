@@ -0,0 +1,589 @@
+use bevy::{
+    asset::{
+        io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadDirectError, LoadState,
+        RecursiveDependencyLoadState,
+    },
+    prelude::*,
+    utils::HashMap,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    ball::{Ball, BallBundle, LastHitBy, BALL_SERVE_OFFSET},
+    block::BlockShape,
+    catalogue::{BrickCatalogue, BrickType, CatalogueLoader, BRICK_CATALOGUE},
+    configure_game_sets,
+    movement::{MovementConfig, MovementConfigError, MovementConfigHandle, MovementOverrides},
+    obstacle::{spawn_brick, Indestructible, Obstacle},
+    player::{LocalPlayers, Player, PlayerBundle, PlayerId, PlayerScore, PADDLE_SIZE},
+    state::{GameState, InLevel},
+    GameSet,
+};
+
+/// Loads `.level.ron` assets, spawns the current level and detects when it is cleared.
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_asset::<Level>()
+            .init_asset::<BrickCatalogue>()
+            .init_asset_loader::<LevelLoader>()
+            .init_asset_loader::<CatalogueLoader>()
+            .add_event::<LevelCleared>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                wait_for_level_system.run_if(in_state(GameState::Loading)),
+            )
+            // Between the two states, so the first tick of the level runs right after it spawns
+            .add_systems(
+                OnTransition {
+                    exited: GameState::Loading,
+                    entered: GameState::Playing,
+                },
+                spawn_level_system,
+            )
+            .add_systems(OnExit(InLevel), despawn_level_system)
+            .add_systems(FixedUpdate, reload_level_system.in_set(GameSet::Spawn))
+            .add_systems(
+                FixedUpdate,
+                (check_level_cleared_system, on_level_cleared_system)
+                    .chain()
+                    .in_set(GameSet::Rules),
+            );
+    }
+}
+
+/// Horizontal gap between the paddles of neighbouring players when a level starts.
+const PLAYER_GAP: f32 = 40.0;
+
+pub const DEFAULT_LEVEL: &str = "levels/01.level.ron";
+
+/// Marks everything spawned from a level file, so it can be cleared when the level is
+/// left or reloaded.
+#[derive(Component)]
+pub struct LevelEntity;
+
+/// Sent once when the last brick of the level has been destroyed.
+#[derive(Event)]
+pub struct LevelCleared;
+
+/// Symbol used in a level layout for an empty cell.
+pub const EMPTY_CELL: char = '.';
+
+/// A level described by a `.level.ron` asset.
+///
+/// Bricks are laid out on a grid centered horizontally on the screen, one string per
+/// row from top to bottom and one character per column, where each character is
+/// either [`EMPTY_CELL`] or a key of `brick_types`, which names a brick of the
+/// [`BRICK_CATALOGUE`].
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub player_spawn: (f32, f32),
+    pub brick_size: (f32, f32),
+    /// Gap between two neighbouring bricks.
+    pub brick_spacing: (f32, f32),
+    /// Vertical position of the center of the top row.
+    pub grid_top: f32,
+    pub brick_types: HashMap<char, String>,
+    pub layout: Vec<String>,
+    /// Changes to the movement config while this level is played.
+    #[serde(default)]
+    pub movement: Option<MovementOverrides>,
+    /// The catalogue the brick names refer to, read along with the level.
+    #[serde(skip)]
+    pub catalogue: BrickCatalogue,
+}
+
+#[derive(Debug, Error)]
+pub enum LevelValidationError {
+    #[error("the layout has no rows")]
+    EmptyLayout,
+    #[error("row {row} has {found} columns, expected {expected} like the first row")]
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("row {row}, column {column} uses brick '{symbol}' which is not in `brick_types`")]
+    UnknownBrick {
+        symbol: char,
+        row: usize,
+        column: usize,
+    },
+    #[error("brick '{symbol}' is a \"{name}\", which is not in the brick catalogue")]
+    UnknownBrickType { symbol: char, name: String },
+    #[error("brick '{0}' uses the symbol reserved for empty cells")]
+    ReservedSymbol(char),
+    #[error("`brick_size` must be positive and finite, got {0:?}")]
+    InvalidBrickSize((f32, f32)),
+    #[error("`brick_spacing` must be finite and not negative, got {0:?}")]
+    InvalidBrickSpacing((f32, f32)),
+    #[error("invalid `movement`: {0}")]
+    InvalidMovement(MovementConfigError),
+}
+
+impl Level {
+    pub fn validate(&self) -> Result<(), LevelValidationError> {
+        // Written so that NaN fails the checks
+        let (width, height) = self.brick_size;
+        if !(width > 0.0 && height > 0.0 && width.is_finite() && height.is_finite()) {
+            return Err(LevelValidationError::InvalidBrickSize(self.brick_size));
+        }
+        let (x, y) = self.brick_spacing;
+        if !(x >= 0.0 && y >= 0.0 && x.is_finite() && y.is_finite()) {
+            return Err(LevelValidationError::InvalidBrickSpacing(
+                self.brick_spacing,
+            ));
+        }
+        if let Some(movement) = &self.movement {
+            movement
+                .validate()
+                .map_err(LevelValidationError::InvalidMovement)?;
+        }
+
+        for (&symbol, name) in self.brick_types.iter() {
+            if symbol == EMPTY_CELL {
+                return Err(LevelValidationError::ReservedSymbol(symbol));
+            }
+            if !self.catalogue.bricks.contains_key(name) {
+                return Err(LevelValidationError::UnknownBrickType {
+                    symbol,
+                    name: name.clone(),
+                });
+            }
+        }
+
+        let Some(first_row) = self.layout.first() else {
+            return Err(LevelValidationError::EmptyLayout);
+        };
+        let expected = first_row.chars().count();
+
+        for (row, cells) in self.layout.iter().enumerate() {
+            let found = cells.chars().count();
+            if found != expected {
+                return Err(LevelValidationError::RaggedRow {
+                    row: row + 1,
+                    expected,
+                    found,
+                });
+            }
+
+            for (column, symbol) in cells.chars().enumerate() {
+                if symbol != EMPTY_CELL && !self.brick_types.contains_key(&symbol) {
+                    return Err(LevelValidationError::UnknownBrick {
+                        symbol,
+                        row: row + 1,
+                        column: column + 1,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Iterates over the center position and type of every brick in the layout.
+    ///
+    /// Must only be called on a validated level.
+    pub fn bricks(&self) -> impl Iterator<Item = (Vec2, &BrickType)> {
+        let size = Vec2::from(self.brick_size);
+        let step = size + Vec2::from(self.brick_spacing);
+        let columns = self.layout.first().map_or(0, |row| row.chars().count());
+        let left = -(columns as f32 - 1.0) * step.x / 2.0;
+
+        self.layout
+            .iter()
+            .enumerate()
+            .flat_map(move |(row, cells)| {
+                cells
+                    .chars()
+                    .enumerate()
+                    .filter(|&(_, symbol)| symbol != EMPTY_CELL)
+                    .map(move |(column, symbol)| {
+                        let position = Vec2::new(
+                            left + column as f32 * step.x,
+                            self.grid_top - row as f32 * step.y,
+                        );
+                        (position, &self.catalogue.bricks[&self.brick_types[&symbol]])
+                    })
+            })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LevelLoaderError {
+    #[error("could not read level file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse level file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("could not load the brick catalogue: {0}")]
+    Catalogue(#[from] LoadDirectError),
+    #[error("invalid level \"{name}\": {source}")]
+    Invalid {
+        name: String,
+        source: LevelValidationError,
+    },
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Level, LevelLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let mut level: Level = ron::de::from_bytes(&bytes)?;
+        // A load dependency, so editing the catalogue reloads the level too
+        level.catalogue = load_context
+            .loader()
+            .direct()
+            .load::<BrickCatalogue>(BRICK_CATALOGUE)
+            .await?
+            .take();
+        level
+            .validate()
+            .map_err(|source| LevelLoaderError::Invalid {
+                name: level.name.clone(),
+                source,
+            })?;
+
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// The level being played and whether its entities have been spawned yet.
+#[derive(Resource)]
+pub struct CurrentLevel {
+    pub handle: Handle<Level>,
+    pub spawned: bool,
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CurrentLevel {
+        handle: asset_server.load(DEFAULT_LEVEL),
+        spawned: false,
+    });
+}
+
+/// Starts the level once its file and the movement config are loaded, or goes back to
+/// the menu if the level cannot be loaded.
+fn wait_for_level_system(
+    current_level: Res<CurrentLevel>,
+    asset_server: Res<AssetServer>,
+    movement_config_handle: Res<MovementConfigHandle>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // The level is spawned with the movement models of the config, and a config loaded
+    // after the first ticks would make replays diverge
+    let movement_config_settled = matches!(
+        asset_server.load_state(&movement_config_handle.0),
+        LoadState::Loaded | LoadState::Failed(_)
+    );
+
+    match asset_server.recursive_dependency_load_state(&current_level.handle) {
+        RecursiveDependencyLoadState::Loaded if movement_config_settled => {
+            next_state.set(GameState::Playing);
+        }
+        RecursiveDependencyLoadState::Failed => {
+            error!("The level could not be loaded");
+            next_state.set(GameState::MainMenu);
+        }
+        _ => {}
+    }
+}
+
+fn spawn_level_system(
+    mut commands: Commands,
+    mut current_level: ResMut<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    movement_config: Res<MovementConfig>,
+    local_players: Res<LocalPlayers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+
+    spawn_level(
+        &mut commands,
+        level,
+        &movement_config,
+        *local_players,
+        &mut meshes,
+        &mut materials,
+    );
+    current_level.spawned = true;
+}
+
+/// Replaces the level entities when the level file is edited during the game.
+#[allow(clippy::too_many_arguments)]
+fn reload_level_system(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<Level>>,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    level_entities: Query<Entity, With<LevelEntity>>,
+    movement_config: Res<MovementConfig>,
+    local_players: Res<LocalPlayers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let modified = asset_events.read().any(
+        |event| matches!(event, AssetEvent::Modified { id } if *id == current_level.handle.id()),
+    );
+    if !modified {
+        return;
+    }
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+
+    for entity in level_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_level(
+        &mut commands,
+        level,
+        &movement_config,
+        *local_players,
+        &mut meshes,
+        &mut materials,
+    );
+}
+
+fn despawn_level_system(
+    mut commands: Commands,
+    mut current_level: ResMut<CurrentLevel>,
+    level_entities: Query<Entity, With<LevelEntity>>,
+) {
+    for entity in level_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    current_level.spawned = false;
+}
+
+/// Spawns the players, their balls and the bricks of `level`.
+fn spawn_level(
+    commands: &mut Commands,
+    level: &Level,
+    movement_config: &MovementConfig,
+    local_players: LocalPlayers,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    info!("Loading level \"{}\"", level.name);
+
+    // Line the players up side by side around the spawn point, each with a ball of
+    // their color above their paddle
+    let player_spawn = Vec2::from(level.player_spawn);
+    let spacing = PADDLE_SIZE.x + PLAYER_GAP;
+    let first_offset = (local_players.count() - 1) as f32 * spacing / -2.0;
+    for player in local_players.ids() {
+        let position = player_spawn + Vec2::X * (first_offset + player.index() as f32 * spacing);
+        let player_entity = commands
+            .spawn((
+                PlayerBundle::new(
+                    player,
+                    position,
+                    movement_config.model_for(player),
+                    materials.add(player.color()),
+                    meshes,
+                ),
+                LevelEntity,
+            ))
+            .id();
+
+        commands.spawn((
+            BallBundle {
+                last_hit_by: LastHitBy(Some(player_entity)),
+                ..BallBundle::new(
+                    position + BALL_SERVE_OFFSET,
+                    materials.add(player.ball_color()),
+                    meshes,
+                )
+            },
+            LevelEntity,
+        ));
+    }
+
+    // Spawn the bricks described by the level layout
+    let brick_shape = BlockShape::new(Vec2::from(level.brick_size));
+    for (position, brick_type) in level.bricks() {
+        spawn_brick(
+            commands,
+            brick_type,
+            brick_shape,
+            position,
+            meshes,
+            materials,
+        )
+        .insert(LevelEntity);
+    }
+}
+
+fn check_level_cleared_system(
+    current_level: Res<CurrentLevel>,
+    bricks: Query<(), (With<Obstacle>, Without<Indestructible>)>,
+    mut level_cleared: EventWriter<LevelCleared>,
+    mut cleared: Local<bool>,
+) {
+    if !current_level.spawned {
+        return;
+    }
+
+    if bricks.is_empty() && !*cleared {
+        level_cleared.send(LevelCleared);
+    }
+
+    *cleared = bricks.is_empty();
+}
+
+fn on_level_cleared_system(
+    mut commands: Commands,
+    mut level_cleared: EventReader<LevelCleared>,
+    balls: Query<Entity, With<Ball>>,
+    players: Query<(&PlayerId, &PlayerScore), With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if level_cleared.read().next().is_none() {
+        return;
+    }
+
+    info!("All bricks destroyed, you win!");
+    let mut scores: Vec<_> = players.iter().collect();
+    scores.sort_by_key(|(id, _)| **id);
+    for (id, score) in scores {
+        info!("Player {}: {} points", id.0 + 1, score.0);
+    }
+    for ball in balls.iter() {
+        commands.entity(ball).despawn_recursive();
+    }
+    next_state.set(GameState::LevelComplete);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(layout: &[&str]) -> Level {
+        let brick = BrickType {
+            color: (1.0, 1.0, 1.0),
+            hit_points: 1,
+            indestructible: false,
+            explosion_radius: None,
+            path: None,
+        };
+        Level {
+            name: "test".to_string(),
+            player_spawn: (0.0, -250.0),
+            brick_size: (60.0, 20.0),
+            brick_spacing: (4.0, 4.0),
+            grid_top: 250.0,
+            brick_types: HashMap::from_iter([('#', "basic".to_string())]),
+            layout: layout.iter().map(|row| row.to_string()).collect(),
+            movement: None,
+            catalogue: BrickCatalogue {
+                bricks: HashMap::from_iter([("basic".to_string(), brick)]),
+            },
+        }
+    }
+
+    #[test]
+    fn valid_level() {
+        assert!(level(&["#.#", ".#."]).validate().is_ok());
+    }
+
+    #[test]
+    fn ragged_rows() {
+        assert!(matches!(
+            level(&["#.#", "#."]).validate(),
+            Err(LevelValidationError::RaggedRow {
+                row: 2,
+                expected: 3,
+                found: 2,
+            })
+        ));
+        assert!(matches!(
+            level(&[]).validate(),
+            Err(LevelValidationError::EmptyLayout)
+        ));
+    }
+
+    #[test]
+    fn unknown_symbols() {
+        assert!(matches!(
+            level(&["#.#", ".x."]).validate(),
+            Err(LevelValidationError::UnknownBrick {
+                symbol: 'x',
+                row: 2,
+                column: 2,
+            })
+        ));
+
+        let mut unknown_type = level(&["#"]);
+        unknown_type.brick_types.insert('#', "missing".to_string());
+        assert!(matches!(
+            unknown_type.validate(),
+            Err(LevelValidationError::UnknownBrickType { symbol: '#', .. })
+        ));
+    }
+
+    #[test]
+    fn reserved_symbol() {
+        let mut reserved = level(&["#"]);
+        reserved.brick_types.insert(EMPTY_CELL, "basic".to_string());
+        assert!(matches!(
+            reserved.validate(),
+            Err(LevelValidationError::ReservedSymbol(EMPTY_CELL))
+        ));
+    }
+
+    #[test]
+    fn bad_sizes() {
+        for brick_size in [
+            (0.0, 20.0),
+            (60.0, -1.0),
+            (f32::NAN, 20.0),
+            (f32::INFINITY, 20.0),
+        ] {
+            let mut bad = level(&["#"]);
+            bad.brick_size = brick_size;
+            assert!(matches!(
+                bad.validate(),
+                Err(LevelValidationError::InvalidBrickSize(_))
+            ));
+        }
+
+        for brick_spacing in [(-1.0, 4.0), (4.0, f32::NAN), (f32::INFINITY, 4.0)] {
+            let mut bad = level(&["#"]);
+            bad.brick_spacing = brick_spacing;
+            assert!(matches!(
+                bad.validate(),
+                Err(LevelValidationError::InvalidBrickSpacing(_))
+            ));
+        }
+
+        let mut no_spacing = level(&["#"]);
+        no_spacing.brick_spacing = (0.0, 0.0);
+        assert!(no_spacing.validate().is_ok());
+    }
+}
@@ -0,0 +1,116 @@
+//! Block breaker gameplay, split into Bevy plugins that can be embedded in other apps.
+//!
+//! [`GamePlugins`] adds the whole game. Reading player input is left to the app, which
+//! should add an [`InputManagerPlugin<Action>`](leafwing_input_manager::prelude::InputManagerPlugin)
+//! next to it.
+
+use bevy::{app::PluginGroupBuilder, prelude::*};
+use bevy_rapier2d::plugin::PhysicsSet;
+
+pub mod arena;
+pub mod ball;
+pub mod bindings;
+pub mod block;
+pub mod camera;
+pub mod catalogue;
+pub mod collision;
+pub mod headless;
+pub mod highscore;
+pub mod level;
+pub mod menu;
+pub mod movement;
+pub mod obstacle;
+pub mod overlay;
+pub mod pause;
+pub mod player;
+pub mod powerup;
+pub mod replay;
+pub mod rng;
+pub mod score;
+pub mod simulation;
+pub mod state;
+pub mod storage;
+
+pub use arena::ArenaPlugin;
+pub use ball::BallPlugin;
+pub use bindings::BindingsPlugin;
+pub use block::BlockPlugin;
+pub use camera::CameraPlugin;
+pub use collision::CollisionPlugin;
+pub use highscore::HighScorePlugin;
+pub use level::LevelPlugin;
+pub use menu::MenuPlugin;
+pub use movement::MovementPlugin;
+pub use obstacle::ObstaclePlugin;
+pub use overlay::OverlayPlugin;
+pub use pause::PausePlugin;
+pub use player::{Action, PlayerPlugin};
+pub use powerup::PowerUpPlugin;
+pub use replay::ReplayPlugin;
+pub use score::ScorePlugin;
+pub use simulation::SimulationPlugin;
+pub use state::{GameState, GameStatePlugin};
+
+/// All the plugins making up the game.
+pub struct GamePlugins;
+
+impl PluginGroup for GamePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(GameStatePlugin)
+            .add(SimulationPlugin)
+            .add(ArenaPlugin)
+            .add(CameraPlugin)
+            .add(BlockPlugin)
+            .add(LevelPlugin)
+            .add(MovementPlugin)
+            .add(PlayerPlugin)
+            .add(BindingsPlugin)
+            .add(CollisionPlugin)
+            .add(BallPlugin)
+            .add(ObstaclePlugin)
+            .add(ScorePlugin)
+            .add(PowerUpPlugin)
+            .add(HighScorePlugin)
+            .add(ReplayPlugin)
+            .add(MenuPlugin)
+            .add(PausePlugin)
+            .add(OverlayPlugin)
+    }
+}
+
+/// Order of the gameplay systems within a simulation tick, shared by all the plugins.
+///
+/// Gameplay runs in [`FixedUpdate`] around the physics step, so a run only depends on
+/// the inputs of every tick and not on the frame rate. It only runs while
+/// [`GameState::Playing`].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// Respawning the level entities when the level file changes.
+    Spawn,
+    /// Recording or replaying the players' inputs.
+    Input,
+    /// Moving the players and balls.
+    Movement,
+    /// Turning the contacts of the last physics step into gameplay events.
+    Contacts,
+    /// Reacting to the gameplay events of the contacts.
+    Collisions,
+    /// Checking the win condition.
+    Rules,
+}
+
+pub(crate) fn configure_game_sets(app: &mut App) {
+    app.configure_sets(
+        FixedUpdate,
+        (
+            (GameSet::Spawn, GameSet::Input, GameSet::Movement)
+                .chain()
+                .before(PhysicsSet::SyncBackend),
+            (GameSet::Contacts, GameSet::Collisions, GameSet::Rules)
+                .chain()
+                .after(PhysicsSet::Writeback),
+        )
+            .run_if(in_state(GameState::Playing)),
+    );
+}
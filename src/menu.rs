@@ -0,0 +1,307 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    highscore::{format_time, HighScores, InitialsEntry, LatestHighScore},
+    state::GameState,
+};
+
+/// The screens shown outside of the game: main menu, leaderboard, loading, pause, level
+/// complete and game over. Each screen is spawned when its state is entered and
+/// despawned when it is left.
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(OnEnter(GameState::Leaderboard), spawn_leaderboard)
+            .add_systems(OnEnter(GameState::Loading), spawn_loading_screen)
+            .add_systems(OnEnter(GameState::Paused), spawn_pause_menu)
+            .add_systems(
+                OnEnter(GameState::LevelComplete),
+                spawn_level_complete_screen,
+            )
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen)
+            .add_systems(
+                Update,
+                // The keys typing the initials of a high score are not for the menus
+                menu_button_system.run_if(not(resource_exists::<InitialsEntry>)),
+            );
+    }
+}
+
+/// Confirms the default button of the screen.
+const CONFIRM_KEYS: [KeyCode; 2] = [KeyCode::Enter, KeyCode::Space];
+
+/// What a menu button does when pressed.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuButton {
+    /// Loads the current level from the start.
+    Play,
+    Resume,
+    /// Loads the current level from the start, from the pause menu.
+    Restart,
+    HighScores,
+    MainMenu,
+    Quit,
+}
+
+impl MenuButton {
+    fn label(self) -> &'static str {
+        match self {
+            MenuButton::Play => "Play",
+            MenuButton::Resume => "Resume",
+            MenuButton::Restart => "Restart",
+            MenuButton::HighScores => "High scores",
+            MenuButton::MainMenu => "Main menu",
+            MenuButton::Quit => "Quit",
+        }
+    }
+}
+
+/// The button pressed by the confirm keys or the south gamepad button.
+#[derive(Component)]
+struct DefaultButton;
+
+fn spawn_main_menu(mut commands: Commands) {
+    spawn_screen(
+        &mut commands,
+        GameState::MainMenu,
+        "Block Bracker",
+        &[MenuButton::Play, MenuButton::HighScores, MenuButton::Quit],
+    );
+}
+
+/// Shows the table of every level that has one, the latest score entered highlighted.
+fn spawn_leaderboard(
+    mut commands: Commands,
+    high_scores: Res<HighScores>,
+    latest: Res<LatestHighScore>,
+) {
+    let screen = spawn_screen(
+        &mut commands,
+        GameState::Leaderboard,
+        "High scores",
+        &[MenuButton::MainMenu],
+    );
+
+    let text_style = TextStyle {
+        font_size: 20.0,
+        ..default()
+    };
+    let mut tables = commands.spawn(NodeBundle {
+        style: Style {
+            column_gap: Val::Px(32.0),
+            ..default()
+        },
+        ..default()
+    });
+    tables.with_children(|tables| {
+        if high_scores.levels.is_empty() {
+            tables.spawn(TextBundle::from_section(
+                "No high scores yet",
+                text_style.clone(),
+            ));
+        }
+
+        for (level, entries) in &high_scores.levels {
+            tables
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|table| {
+                    table.spawn(TextBundle::from_section(
+                        level.as_str(),
+                        TextStyle {
+                            font_size: 28.0,
+                            ..default()
+                        },
+                    ));
+                    table
+                        .spawn(NodeBundle {
+                            style: Style {
+                                display: Display::Grid,
+                                grid_template_columns: RepeatedGridTrack::auto(5),
+                                column_gap: Val::Px(16.0),
+                                row_gap: Val::Px(4.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|rows| {
+                            for (rank, entry) in entries.iter().enumerate() {
+                                let highlighted =
+                                    latest.0.as_ref().is_some_and(|(latest, index)| {
+                                        latest == level && *index == rank
+                                    });
+                                let style = TextStyle {
+                                    color: if highlighted {
+                                        Color::srgb(1.0, 0.8, 0.2)
+                                    } else {
+                                        Color::WHITE
+                                    },
+                                    ..text_style.clone()
+                                };
+
+                                for cell in [
+                                    format!("{}.", rank + 1),
+                                    entry.name.clone(),
+                                    entry.score.to_string(),
+                                    format_time(entry.time),
+                                    entry.date.clone(),
+                                ] {
+                                    rows.spawn(TextBundle::from_section(cell, style.clone()));
+                                }
+                            }
+                        });
+                });
+        }
+    });
+
+    // Between the title and the buttons
+    let tables = tables.id();
+    commands.entity(screen).insert_children(1, &[tables]);
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    spawn_screen(&mut commands, GameState::Loading, "Loading...", &[]);
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    spawn_screen(
+        &mut commands,
+        GameState::Paused,
+        "Paused",
+        &[MenuButton::Resume, MenuButton::Restart, MenuButton::Quit],
+    );
+}
+
+fn spawn_level_complete_screen(mut commands: Commands) {
+    spawn_screen(
+        &mut commands,
+        GameState::LevelComplete,
+        "Level complete!",
+        &[
+            MenuButton::Play,
+            MenuButton::HighScores,
+            MenuButton::MainMenu,
+        ],
+    );
+}
+
+fn spawn_game_over_screen(mut commands: Commands) {
+    spawn_screen(
+        &mut commands,
+        GameState::GameOver,
+        "Game over",
+        &[
+            MenuButton::Play,
+            MenuButton::HighScores,
+            MenuButton::MainMenu,
+        ],
+    );
+}
+
+/// Spawns a centered title above a column of buttons, the first one being the default,
+/// all despawned when leaving `state`. Returns the screen entity.
+fn spawn_screen(
+    commands: &mut Commands,
+    state: GameState,
+    title: &str,
+    buttons: &[MenuButton],
+) -> Entity {
+    commands
+        .spawn((
+            StateScoped(state),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    align_self: AlignSelf::Center,
+                    justify_self: JustifySelf::Center,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.0),
+                    padding: UiRect::all(Val::Px(24.0)),
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.85).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 48.0,
+                    ..default()
+                },
+            ));
+
+            for (index, &button) in buttons.iter().enumerate() {
+                let mut entity = screen.spawn((
+                    button,
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(200.0),
+                            justify_content: JustifyContent::Center,
+                            padding: UiRect::all(Val::Px(8.0)),
+                            ..default()
+                        },
+                        background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+                        ..default()
+                    },
+                ));
+                if index == 0 {
+                    entity.insert(DefaultButton);
+                }
+                entity.with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        button.label(),
+                        TextStyle {
+                            font_size: 24.0,
+                            ..default()
+                        },
+                    ));
+                });
+            }
+        })
+        .id()
+}
+
+fn menu_button_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    default_buttons: Query<&MenuButton, With<DefaultButton>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let confirmed = keys.any_just_pressed(CONFIRM_KEYS)
+        || gamepad_buttons
+            .get_just_pressed()
+            .any(|button| button.button_type == GamepadButtonType::South);
+
+    let clicked = buttons
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button);
+    let confirmed = default_buttons.iter().filter(|_| confirmed);
+
+    for button in clicked.chain(confirmed) {
+        match button {
+            MenuButton::Play | MenuButton::Restart => next_state.set(GameState::Loading),
+            MenuButton::Resume => next_state.set(GameState::Playing),
+            MenuButton::HighScores => next_state.set(GameState::Leaderboard),
+            MenuButton::MainMenu => next_state.set(GameState::MainMenu),
+            MenuButton::Quit => {
+                exit.send(AppExit::Success);
+            }
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    level::{CurrentLevel, Level},
+    player::{PlayerId, MAX_PLAYERS},
+};
+
+/// Loads the [`MovementConfig`] from `.movement.ron` assets and applies the overrides of
+/// the current level on top of it.
+///
+/// Edits to the config file are picked up while the game is running.
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<MovementConfig>()
+            .init_asset_loader::<MovementConfigLoader>()
+            .init_resource::<MovementConfig>()
+            .add_systems(Startup, setup)
+            // Before the state transitions, so a level never spawns with a stale config
+            .add_systems(PreUpdate, update_movement_config_system);
+    }
+}
+
+pub const MOVEMENT_CONFIG: &str = "config/default.movement.ron";
+
+/// How a player's input turns into motion.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum MovementModel {
+    /// Velocity snaps to the input: full speed while held, immediate stop on release.
+    #[default]
+    Arcade,
+    /// Input accelerates the player, who keeps drifting and spinning after release.
+    Inertial,
+}
+
+/// Current linear and angular velocity of a player, in pixels and radians per second.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct PlayerMotion {
+    pub velocity: Vec2,
+    pub angular_velocity: f32,
+}
+
+impl PlayerMotion {
+    /// Updates the velocities from the input for `dt` seconds.
+    ///
+    /// `direction` is a vector of length at most 1, `rotation` is in `-1.0..=1.0`
+    /// with positive values turning counterclockwise.
+    pub fn steer(
+        &mut self,
+        model: MovementModel,
+        direction: Vec2,
+        rotation: f32,
+        config: &MovementConfig,
+        dt: f32,
+    ) {
+        match model {
+            MovementModel::Arcade => {
+                self.velocity = direction * config.speed;
+                self.angular_velocity = rotation * config.rotation_speed;
+            }
+            MovementModel::Inertial => {
+                self.steer_linear(direction, config, dt);
+                self.steer_angular(rotation, config, dt);
+            }
+        }
+    }
+
+    fn steer_linear(&mut self, direction: Vec2, config: &MovementConfig, dt: f32) {
+        if direction != Vec2::ZERO && config.speed > 0.0 {
+            // Acceleration fades out as the player nears top speed in the input direction,
+            // the curve exponent controls how late that happens
+            let progress = (self.velocity.dot(direction) / config.speed).clamp(0.0, 1.0);
+            let falloff = 1.0 - progress.powf(config.acceleration_curve);
+            self.velocity += direction * config.acceleration * falloff * dt;
+        } else {
+            self.velocity = move_towards(self.velocity, Vec2::ZERO, config.friction * dt);
+        }
+
+        self.velocity *= (1.0 - config.drag * dt).max(0.0);
+        self.velocity = self.velocity.clamp_length_max(config.speed);
+    }
+
+    fn steer_angular(&mut self, rotation: f32, config: &MovementConfig, dt: f32) {
+        self.angular_velocity += rotation * config.angular_acceleration * dt;
+        self.angular_velocity *= (1.0 - config.angular_drag * dt).max(0.0);
+        self.angular_velocity = self
+            .angular_velocity
+            .clamp(-config.max_angular_velocity, config.max_angular_velocity);
+    }
+}
+
+fn move_towards(current: Vec2, target: Vec2, max_delta: f32) -> Vec2 {
+    let delta = target - current;
+    if delta.length() <= max_delta {
+        target
+    } else {
+        current + delta.normalize() * max_delta
+    }
+}
+
+/// Tuning of the player movement, in pixels and radians per second.
+///
+/// The resource holds the config in effect, the asset holds the config read from disk
+/// before the level overrides are applied.
+#[derive(Resource, Asset, TypePath, Clone, Debug, Deserialize)]
+pub struct MovementConfig {
+    /// Model given to the players when they spawn.
+    #[serde(default)]
+    pub model: MovementModel,
+    /// Models of the players who do not get [`MovementConfig::model`], by player slot
+    /// from 0, to try both feels side by side.
+    #[serde(default)]
+    pub player_models: BTreeMap<usize, MovementModel>,
+    /// Top speed of the player.
+    pub speed: f32,
+    /// How fast inertial players build up speed, in pixels per second squared.
+    pub acceleration: f32,
+    /// Exponent shaping how the acceleration of inertial players fades out near top speed,
+    /// higher values keep it strong for longer.
+    pub acceleration_curve: f32,
+    /// How fast inertial players slow down without input, in pixels per second squared.
+    pub friction: f32,
+    /// Fraction of their velocity inertial players lose every second, input or not.
+    pub drag: f32,
+    /// Rotation speed of arcade players while a rotation action is held.
+    pub rotation_speed: f32,
+    /// How fast inertial players build up spin, in radians per second squared.
+    pub angular_acceleration: f32,
+    /// Fraction of their spin inertial players lose every second.
+    pub angular_drag: f32,
+    /// Cap on the spin inertial players can build up, in radians per second.
+    pub max_angular_velocity: f32,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            model: MovementModel::Arcade,
+            player_models: BTreeMap::new(),
+            speed: 200.0,
+            acceleration: 1200.0,
+            acceleration_curve: 2.0,
+            friction: 800.0,
+            drag: 0.5,
+            rotation_speed: std::f32::consts::PI / 2.0,
+            angular_acceleration: 2.0 * std::f32::consts::PI,
+            angular_drag: 1.0,
+            max_angular_velocity: std::f32::consts::PI,
+        }
+    }
+}
+
+impl MovementConfig {
+    /// Model given to `player` when they spawn.
+    pub fn model_for(&self, player: PlayerId) -> MovementModel {
+        self.player_models
+            .get(&player.index())
+            .copied()
+            .unwrap_or(self.model)
+    }
+
+    pub fn validate(&self) -> Result<(), MovementConfigError> {
+        if let Some(&player) = self
+            .player_models
+            .keys()
+            .find(|&&player| player >= MAX_PLAYERS)
+        {
+            return Err(MovementConfigError::UnknownPlayer(player));
+        }
+
+        let fields = [
+            ("speed", self.speed),
+            ("acceleration", self.acceleration),
+            ("acceleration_curve", self.acceleration_curve),
+            ("friction", self.friction),
+            ("drag", self.drag),
+            ("rotation_speed", self.rotation_speed),
+            ("angular_acceleration", self.angular_acceleration),
+            ("angular_drag", self.angular_drag),
+            ("max_angular_velocity", self.max_angular_velocity),
+        ];
+
+        match fields
+            .into_iter()
+            .find(|(_, value)| !value.is_finite() || *value < 0.0)
+        {
+            Some((field, value)) => Err(MovementConfigError::OutOfRange { field, value }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns this config with the fields set in `overrides` replaced.
+    ///
+    /// A level setting `model` gives it to every player, only its own `player_models`
+    /// then tell players apart.
+    pub fn with_overrides(&self, overrides: &MovementOverrides) -> Self {
+        let mut player_models = match overrides.model {
+            Some(_) => BTreeMap::new(),
+            None => self.player_models.clone(),
+        };
+        player_models.extend(&overrides.player_models);
+
+        Self {
+            model: overrides.model.unwrap_or(self.model),
+            player_models,
+            speed: overrides.speed.unwrap_or(self.speed),
+            acceleration: overrides.acceleration.unwrap_or(self.acceleration),
+            acceleration_curve: overrides
+                .acceleration_curve
+                .unwrap_or(self.acceleration_curve),
+            friction: overrides.friction.unwrap_or(self.friction),
+            drag: overrides.drag.unwrap_or(self.drag),
+            rotation_speed: overrides.rotation_speed.unwrap_or(self.rotation_speed),
+            angular_acceleration: overrides
+                .angular_acceleration
+                .unwrap_or(self.angular_acceleration),
+            angular_drag: overrides.angular_drag.unwrap_or(self.angular_drag),
+            max_angular_velocity: overrides
+                .max_angular_velocity
+                .unwrap_or(self.max_angular_velocity),
+        }
+    }
+}
+
+/// Per-level changes to the [`MovementConfig`], unset fields keep the configured value.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MovementOverrides {
+    pub model: Option<MovementModel>,
+    pub player_models: BTreeMap<usize, MovementModel>,
+    pub speed: Option<f32>,
+    pub acceleration: Option<f32>,
+    pub acceleration_curve: Option<f32>,
+    pub friction: Option<f32>,
+    pub drag: Option<f32>,
+    pub rotation_speed: Option<f32>,
+    pub angular_acceleration: Option<f32>,
+    pub angular_drag: Option<f32>,
+    pub max_angular_velocity: Option<f32>,
+}
+
+impl MovementOverrides {
+    /// Checks the fields set like [`MovementConfig::validate`] does.
+    pub fn validate(&self) -> Result<(), MovementConfigError> {
+        MovementConfig::default().with_overrides(self).validate()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MovementConfigError {
+    #[error("could not read movement config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse movement config: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("`{field}` must be a finite, non-negative number, got {value}")]
+    OutOfRange { field: &'static str, value: f32 },
+    #[error("`player_models` sets a model for player {0}, there are only {MAX_PLAYERS} players")]
+    UnknownPlayer(usize),
+}
+
+#[derive(Default)]
+pub struct MovementConfigLoader;
+
+impl AssetLoader for MovementConfigLoader {
+    type Asset = MovementConfig;
+    type Settings = ();
+    type Error = MovementConfigError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<MovementConfig, MovementConfigError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let config: MovementConfig = ron::de::from_bytes(&bytes)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["movement.ron"]
+    }
+}
+
+/// Handle keeping the movement config file loaded.
+#[derive(Resource)]
+pub struct MovementConfigHandle(pub Handle<MovementConfig>);
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(MovementConfigHandle(asset_server.load(MOVEMENT_CONFIG)));
+}
+
+/// Recomputes the [`MovementConfig`] resource when the config file or the current level
+/// is (re)loaded.
+fn update_movement_config_system(
+    mut config_events: EventReader<AssetEvent<MovementConfig>>,
+    mut level_events: EventReader<AssetEvent<Level>>,
+    handle: Res<MovementConfigHandle>,
+    configs: Res<Assets<MovementConfig>>,
+    current_level: Option<Res<CurrentLevel>>,
+    levels: Res<Assets<Level>>,
+    mut movement_config: ResMut<MovementConfig>,
+) {
+    let config_loaded = config_events.read().any(
+        |event| matches!(event, AssetEvent::LoadedWithDependencies { id } if *id == handle.0.id()),
+    );
+    let level_loaded = level_events.read().any(|event| {
+        matches!(event, AssetEvent::LoadedWithDependencies { id }
+            if current_level.as_ref().is_some_and(|level| *id == level.handle.id()))
+    });
+    if !config_loaded && !level_loaded {
+        return;
+    }
+
+    let base = configs.get(&handle.0).cloned().unwrap_or_default();
+    let level = current_level
+        .as_ref()
+        .and_then(|current_level| levels.get(&current_level.handle));
+
+    *movement_config = match level.and_then(|level| level.movement.as_ref()) {
+        Some(overrides) => base.with_overrides(overrides),
+        None => base,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_are_validated() {
+        assert!(MovementOverrides::default().validate().is_ok());
+
+        let negative = MovementOverrides {
+            max_angular_velocity: Some(-1.0),
+            ..default()
+        };
+        assert!(matches!(
+            negative.validate(),
+            Err(MovementConfigError::OutOfRange {
+                field: "max_angular_velocity",
+                ..
+            })
+        ));
+
+        let nan = MovementOverrides {
+            drag: Some(f32::NAN),
+            ..default()
+        };
+        assert!(matches!(
+            nan.validate(),
+            Err(MovementConfigError::OutOfRange { field: "drag", .. })
+        ));
+
+        let unknown_player = MovementOverrides {
+            player_models: BTreeMap::from([(MAX_PLAYERS, MovementModel::Inertial)]),
+            ..default()
+        };
+        assert!(matches!(
+            unknown_player.validate(),
+            Err(MovementConfigError::UnknownPlayer(MAX_PLAYERS))
+        ));
+    }
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn steer_for(
+        motion: &mut PlayerMotion,
+        direction: Vec2,
+        rotation: f32,
+        config: &MovementConfig,
+        ticks: usize,
+    ) {
+        for _ in 0..ticks {
+            motion.steer(MovementModel::Inertial, direction, rotation, config, DT);
+        }
+    }
+
+    #[test]
+    fn inertial_speed_is_capped() {
+        let config = MovementConfig {
+            drag: 0.0,
+            ..default()
+        };
+        let mut motion = PlayerMotion::default();
+        steer_for(&mut motion, Vec2::X, 0.0, &config, 600);
+        assert!(motion.velocity.length() <= config.speed + 1e-3);
+        assert!(motion.velocity.x > 0.9 * config.speed);
+        assert_eq!(motion.velocity.y, 0.0);
+
+        // Even when starting above it, the cap holds
+        let mut motion = PlayerMotion {
+            velocity: Vec2::new(-10.0 * config.speed, 0.0),
+            angular_velocity: 0.0,
+        };
+        steer_for(&mut motion, Vec2::NEG_X, 0.0, &config, 1);
+        assert!(motion.velocity.length() <= config.speed + 1e-3);
+    }
+
+    #[test]
+    fn inertial_motion_stops_without_input() {
+        let config = MovementConfig::default();
+        let mut motion = PlayerMotion {
+            velocity: Vec2::new(config.speed, 0.0),
+            angular_velocity: config.max_angular_velocity,
+        };
+        steer_for(&mut motion, Vec2::ZERO, 0.0, &config, 1);
+        assert!(motion.velocity.x < config.speed);
+        assert!(motion.velocity.x > 0.0);
+
+        steer_for(&mut motion, Vec2::ZERO, 0.0, &config, 600);
+        assert_eq!(motion.velocity, Vec2::ZERO);
+        assert!(motion.angular_velocity.abs() < 1e-3);
+    }
+
+    #[test]
+    fn inertial_spin_is_capped() {
+        let config = MovementConfig {
+            angular_drag: 0.0,
+            ..default()
+        };
+        let mut motion = PlayerMotion::default();
+        steer_for(&mut motion, Vec2::ZERO, 1.0, &config, 600);
+        assert_eq!(motion.angular_velocity, config.max_angular_velocity);
+
+        steer_for(&mut motion, Vec2::ZERO, -1.0, &config, 1200);
+        assert_eq!(motion.angular_velocity, -config.max_angular_velocity);
+    }
+}
@@ -0,0 +1,341 @@
+use bevy::{
+    ecs::system::EntityCommands,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    ball::{Ball, LastHitBy},
+    block::{BlockBundle, BlockShape},
+    catalogue::{BrickType, PathType},
+    collision::{BallHitBrick, LaserHitBrick},
+    configure_game_sets,
+    powerup::Laser,
+    simulation::InterpolatedTransform,
+    GameSet,
+};
+
+/// Moves, damages and destroys the bricks: a brick hit by a ball or a laser shot loses a
+/// hit point and is destroyed once out of them, taking a hit point from the bricks around
+/// it if it is explosive.
+pub struct ObstaclePlugin;
+
+impl Plugin for ObstaclePlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.add_event::<BrickDestroyed>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    move_bricks_system.in_set(GameSet::Movement),
+                    damage_bricks_system.in_set(GameSet::Collisions),
+                ),
+            )
+            .add_systems(Update, darken_damaged_bricks_system);
+    }
+}
+
+/// How much darker a brick gets once it lost all but its last hit point.
+const DAMAGED_DARKENING: f32 = 0.5;
+/// Width of the border around the inset of indestructible bricks.
+const INDESTRUCTIBLE_BORDER: f32 = 4.0;
+const EXPLOSIVE_CORE_COLOR: Color = Color::srgb(1.0, 0.9, 0.3);
+const MOVING_STRIPE_HEIGHT: f32 = 4.0;
+
+/// Sent when a brick is destroyed.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BrickDestroyed {
+    /// The player who last hit the ball or fired the laser, if any.
+    pub player: Option<Entity>,
+    /// Where the brick was.
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+pub struct Obstacle;
+
+/// Number of hits from balls and laser shots a brick can take before it is destroyed.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HitPoints {
+    pub remaining: u32,
+    pub max: u32,
+}
+
+impl HitPoints {
+    pub fn new(max: u32) -> Self {
+        Self {
+            remaining: max,
+            max,
+        }
+    }
+}
+
+/// A brick no ball or laser can destroy, its hit points are ignored.
+#[derive(Component)]
+pub struct Indestructible;
+
+/// A brick taking a hit point from every other brick within `radius` of its center when
+/// destroyed.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Explosive {
+    pub radius: f32,
+}
+
+/// A brick moving in a loop through `waypoints` at `speed` pixels per second.
+#[derive(Component, Clone, Debug)]
+pub struct BrickPath {
+    pub waypoints: Vec<Vec2>,
+    pub speed: f32,
+    /// Index of the waypoint the brick is heading to.
+    pub next: usize,
+}
+
+impl BrickPath {
+    /// The path of `path` for a brick starting at `start`, which it comes back to after
+    /// the last waypoint.
+    pub fn new(start: Vec2, path: &PathType) -> Self {
+        let mut waypoints: Vec<Vec2> = path
+            .waypoints
+            .iter()
+            .map(|&offset| start + Vec2::from(offset))
+            .collect();
+        waypoints.push(start);
+
+        Self {
+            waypoints,
+            speed: path.speed,
+            next: 0,
+        }
+    }
+}
+
+/// Color of an undamaged brick, it darkens as it loses hit points.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BrickColor(pub Color);
+
+/// A brick, a fixed block destroyed after taking `hit_points` hits from balls or lasers.
+#[derive(Bundle)]
+pub struct BrickBundle {
+    pub obstacle: Obstacle,
+    pub block: BlockBundle,
+    pub hit_points: HitPoints,
+    pub body: RigidBody,
+    pub active_events: ActiveEvents,
+}
+
+impl BrickBundle {
+    pub fn new(
+        shape: BlockShape,
+        position: Vec2,
+        hit_points: u32,
+        material: Handle<ColorMaterial>,
+        meshes: &mut Assets<Mesh>,
+    ) -> Self {
+        Self {
+            obstacle: Obstacle,
+            block: BlockBundle::new(
+                shape,
+                material,
+                Transform::from_translation(position.extend(0.0)),
+                meshes,
+            ),
+            hit_points: HitPoints::new(hit_points),
+            body: RigidBody::Fixed,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+        }
+    }
+}
+
+/// Spawns a brick of `brick_type`, with the components and markings of its kind.
+pub fn spawn_brick<'a>(
+    commands: &'a mut Commands,
+    brick_type: &BrickType,
+    shape: BlockShape,
+    position: Vec2,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) -> EntityCommands<'a> {
+    let (r, g, b) = brick_type.color;
+    let color = Color::srgb(r, g, b);
+    let size = shape.size;
+
+    let mut brick = commands.spawn((
+        BrickBundle::new(
+            shape,
+            position,
+            brick_type.hit_points,
+            materials.add(color),
+            meshes,
+        ),
+        BrickColor(color),
+    ));
+
+    // Markings drawn over the brick, telling its kind apart from its color
+    let mut markings = Vec::new();
+    if brick_type.indestructible {
+        brick.insert(Indestructible);
+        markings.push((
+            Mesh::from(Rectangle::from_size(size - 2.0 * INDESTRUCTIBLE_BORDER)),
+            color.darker(0.15),
+        ));
+    }
+    if let Some(radius) = brick_type.explosion_radius {
+        brick.insert(Explosive { radius });
+        markings.push((
+            Mesh::from(Circle::new(size.min_element() / 4.0)),
+            EXPLOSIVE_CORE_COLOR,
+        ));
+    }
+    if let Some(path) = &brick_type.path {
+        // Moved by setting its position, the balls bounce off it as off a moving body
+        brick.insert((
+            BrickPath::new(position, path),
+            RigidBody::KinematicPositionBased,
+            InterpolatedTransform::default(),
+        ));
+        markings.push((
+            Mesh::from(Rectangle::new(
+                size.x - 2.0 * INDESTRUCTIBLE_BORDER,
+                MOVING_STRIPE_HEIGHT,
+            )),
+            color.lighter(0.2),
+        ));
+    }
+
+    brick.with_children(|brick| {
+        for (mesh, color) in markings {
+            brick.spawn(MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(mesh)),
+                material: materials.add(color),
+                transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                ..default()
+            });
+        }
+    });
+
+    brick
+}
+
+fn move_bricks_system(time: Res<Time>, mut bricks: Query<(&mut Transform, &mut BrickPath)>) {
+    for (mut transform, mut path) in bricks.iter_mut() {
+        let mut position = transform.translation.truncate();
+        let mut distance = path.speed * time.delta_seconds();
+
+        // The distance left once at a waypoint carries on to the next one, bounded in
+        // case the waypoints are all on the same spot
+        for _ in 0..path.waypoints.len() {
+            let to_next = path.waypoints[path.next] - position;
+            let length = to_next.length();
+            if length > distance {
+                position += to_next / length * distance;
+                break;
+            }
+
+            position = path.waypoints[path.next];
+            distance -= length;
+            path.next = (path.next + 1) % path.waypoints.len();
+        }
+
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn damage_bricks_system(
+    mut commands: Commands,
+    mut ball_hits: EventReader<BallHitBrick>,
+    mut laser_hits: EventReader<LaserHitBrick>,
+    balls: Query<&LastHitBy, With<Ball>>,
+    lasers: Query<&Laser>,
+    mut bricks: Query<
+        (Entity, &Transform, &mut HitPoints, Option<&Explosive>),
+        (With<Obstacle>, Without<Indestructible>),
+    >,
+    mut destroyed: EventWriter<BrickDestroyed>,
+) {
+    // Bricks to take a hit point from, and the player credited if they are destroyed.
+    // Explosions add the bricks around to the end, so chain reactions play out in order.
+    let mut damaged: Vec<(Entity, Option<Entity>)> = ball_hits
+        .read()
+        .map(|hit| {
+            let player = balls
+                .get(hit.ball)
+                .ok()
+                .and_then(|last_hit_by| last_hit_by.0);
+            (hit.brick, player)
+        })
+        .chain(laser_hits.read().map(|hit| {
+            let player = lasers.get(hit.laser).ok().map(|laser| laser.player);
+            (hit.brick, player)
+        }))
+        .collect();
+
+    let mut index = 0;
+    while let Some(&(brick, player)) = damaged.get(index) {
+        index += 1;
+
+        let Ok((_, transform, mut hit_points, explosive)) = bricks.get_mut(brick) else {
+            continue;
+        };
+
+        // Two hits may destroy the same brick during a tick, only the first one counts
+        if hit_points.remaining == 0 {
+            continue;
+        }
+
+        hit_points.remaining -= 1;
+        if hit_points.remaining > 0 {
+            continue;
+        }
+
+        let center = transform.translation.truncate();
+        commands.entity(brick).despawn_recursive();
+        destroyed.send(BrickDestroyed {
+            player,
+            position: center,
+        });
+
+        let Some(&Explosive { radius }) = explosive else {
+            continue;
+        };
+        damaged.extend(
+            bricks
+                .iter()
+                .filter(|&(other, other_transform, other_hit_points, _)| {
+                    other != brick
+                        && other_hit_points.remaining > 0
+                        && other_transform.translation.truncate().distance(center) <= radius
+                })
+                .map(|(other, ..)| (other, player)),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn darken_damaged_bricks_system(
+    bricks: Query<
+        (&HitPoints, &BrickColor, &Handle<ColorMaterial>),
+        (Changed<HitPoints>, Without<Indestructible>),
+    >,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (hit_points, color, material) in bricks.iter() {
+        // Indestructible bricks may be given no hit points at all
+        if hit_points.max == 0 {
+            continue;
+        }
+        let Some(material) = materials.get_mut(material) else {
+            continue;
+        };
+
+        // From the brick's own color at full hit points to the darkest at its last one
+        let lost = hit_points.max.saturating_sub(hit_points.remaining.max(1));
+        let darkening = if hit_points.max > 1 {
+            DAMAGED_DARKENING * lost as f32 / (hit_points.max - 1) as f32
+        } else {
+            0.0
+        };
+        material.color = color.0.mix(&Color::BLACK, darkening);
+    }
+}
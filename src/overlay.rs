@@ -0,0 +1,216 @@
+use bevy::{
+    prelude::*,
+    window::{CompositeAlphaMode, PrimaryWindow, WindowLevel},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{self, StorageError};
+
+#[cfg(target_os = "linux")]
+use bevy::{
+    ecs::entity::EntityHashSet,
+    render::{
+        renderer::{RenderAdapter, RenderInstance},
+        view::window::{create_surfaces, ExtractedWindows},
+        Render, RenderApp,
+    },
+};
+
+/// Lets the game window be clicked through while in [`DisplayMode::Overlay`], so the
+/// desktop under it stays usable.
+///
+/// The window itself is set up by the app, from [`DisplayMode::window`]. On Linux, the
+/// plugin then picks how the window blends with the desktop from what its surface
+/// supports, see [`OVERLAY_ALPHA_MODES`].
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DisplayMode>()
+            .add_systems(Update, toggle_click_through_system);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn finish(&self, app: &mut App) {
+        if app.world().get_resource::<DisplayMode>() != Some(&DisplayMode::Overlay) {
+            return;
+        }
+        let fallback = app
+            .world()
+            .get_resource::<DisplaySettings>()
+            .map_or_else(default, |settings| settings.overlay_alpha_fallback);
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .insert_resource(OverlayAlphaFallback(fallback))
+            .add_systems(
+                Render,
+                choose_overlay_alpha_mode_system.before(create_surfaces),
+            );
+    }
+}
+
+/// File the [`DisplaySettings`] are read from, in the config directory.
+pub const DISPLAY_FILE: &str = "display.ron";
+/// Makes the overlay window let clicks through to the desktop under it, or catch them
+/// again. The window needs the keyboard focus, which clicking through it gives away:
+/// switch back to it from the taskbar or with the keyboard first.
+pub const CLICK_THROUGH_KEY: KeyCode = KeyCode::F9;
+/// Composite alpha modes blending the overlay window with the desktop on Linux, in order
+/// of preference. The surface of the window is asked which ones it supports when it is
+/// created, [`DisplaySettings::overlay_alpha_fallback`] is used if none.
+pub const OVERLAY_ALPHA_MODES: [CompositeAlphaMode; 2] = [
+    CompositeAlphaMode::PreMultiplied,
+    CompositeAlphaMode::Inherit,
+];
+
+/// How the game is shown on the desktop.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    /// A regular window.
+    #[default]
+    Windowed,
+    /// A transparent window without decorations, kept above the other windows, where
+    /// only the game itself is drawn over the desktop.
+    Overlay,
+}
+
+impl DisplayMode {
+    /// The primary window of the game in this mode.
+    pub fn window(self, title: impl Into<String>) -> Window {
+        let title = title.into();
+        match self {
+            DisplayMode::Windowed => Window { title, ..default() },
+            DisplayMode::Overlay => Window {
+                title,
+                transparent: true,
+                decorations: false,
+                window_level: WindowLevel::AlwaysOnTop,
+                // Metal always blends post-multiplied surfaces with the desktop. Other
+                // platforms keep `Auto`, which wgpu resolves to `Opaque` whenever the surface
+                // supports it. On Linux the plugin replaces it with a blending mode once the
+                // surface tells which ones it supports, asking for one it does not support
+                // would panic
+                #[cfg(target_os = "macos")]
+                composite_alpha_mode: CompositeAlphaMode::PostMultiplied,
+                ..default()
+            },
+        }
+    }
+
+    /// The color the window is cleared to behind the game.
+    pub fn clear_color(self) -> ClearColor {
+        match self {
+            DisplayMode::Windowed => ClearColor::default(),
+            DisplayMode::Overlay => ClearColor(Color::NONE),
+        }
+    }
+}
+
+/// Display options read on launch, before the window is opened. They are not saved by
+/// the game, edit the file to change them.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub mode: DisplayMode,
+    /// How the overlay window blends with the desktop on Linux when its surface supports
+    /// none of the [`OVERLAY_ALPHA_MODES`], only if the surface supports it. `Auto`, the
+    /// default, gives an opaque window.
+    pub overlay_alpha_fallback: CompositeAlphaMode,
+}
+
+impl DisplaySettings {
+    /// Reads the settings from [`DISPLAY_FILE`], the defaults if there is no such file.
+    ///
+    /// Called before the app and its logging are set up, errors are left to the caller
+    /// to report.
+    pub fn load() -> Result<Self, StorageError> {
+        storage::load_or_default(DISPLAY_FILE)
+    }
+}
+
+fn toggle_click_through_system(
+    mode: Res<DisplayMode>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if *mode != DisplayMode::Overlay || !keys.just_pressed(CLICK_THROUGH_KEY) {
+        return;
+    }
+    let Ok(mut window) = window.get_single_mut() else {
+        return;
+    };
+
+    window.cursor.hit_test = !window.cursor.hit_test;
+    info!(
+        "Click-through {}",
+        if window.cursor.hit_test { "off" } else { "on" }
+    );
+}
+
+/// [`DisplaySettings::overlay_alpha_fallback`], in the render world.
+#[cfg(target_os = "linux")]
+#[derive(Resource)]
+struct OverlayAlphaFallback(CompositeAlphaMode);
+
+/// Sets the composite alpha mode of every new window to the first of the
+/// [`OVERLAY_ALPHA_MODES`] its surface supports, right before Bevy creates and
+/// configures that surface with it.
+#[cfg(target_os = "linux")]
+fn choose_overlay_alpha_mode_system(
+    mut windows: ResMut<ExtractedWindows>,
+    instance: Res<RenderInstance>,
+    adapter: Res<RenderAdapter>,
+    fallback: Res<OverlayAlphaFallback>,
+    mut chosen: Local<EntityHashSet>,
+) {
+    for window in windows.windows.values_mut() {
+        if !chosen.insert(window.entity) {
+            continue;
+        }
+
+        // SAFETY: These are the handles Bevy creates the window's own surface from, they
+        // stay valid as long as the window is extracted
+        let surface = unsafe {
+            instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+                raw_display_handle: window.handle.display_handle,
+                raw_window_handle: window.handle.window_handle,
+            })
+        };
+        let supported = match surface {
+            Ok(surface) => surface.get_capabilities(&adapter).alpha_modes,
+            Err(error) => {
+                warn!("Could not check how the overlay can blend with the desktop: {error}");
+                continue;
+            }
+        };
+
+        let mode = OVERLAY_ALPHA_MODES
+            .into_iter()
+            .chain([fallback.0])
+            .find(|&mode| supported.contains(&wgpu_alpha_mode(mode)))
+            .unwrap_or(CompositeAlphaMode::Auto);
+        if OVERLAY_ALPHA_MODES.contains(&mode) {
+            info!("Blending the overlay with the desktop in {mode:?} mode");
+        } else {
+            warn!(
+                "The window surface supports none of {OVERLAY_ALPHA_MODES:?}, only {supported:?}, \
+                 using {mode:?}: the overlay may be opaque"
+            );
+        }
+        window.alpha_mode = mode;
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn wgpu_alpha_mode(mode: CompositeAlphaMode) -> wgpu::CompositeAlphaMode {
+    match mode {
+        CompositeAlphaMode::Auto => wgpu::CompositeAlphaMode::Auto,
+        CompositeAlphaMode::Opaque => wgpu::CompositeAlphaMode::Opaque,
+        CompositeAlphaMode::PreMultiplied => wgpu::CompositeAlphaMode::PreMultiplied,
+        CompositeAlphaMode::PostMultiplied => wgpu::CompositeAlphaMode::PostMultiplied,
+        CompositeAlphaMode::Inherit => wgpu::CompositeAlphaMode::Inherit,
+    }
+}
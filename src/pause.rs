@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    bindings::Rebinding,
+    player::{Action, Player},
+    state::GameState,
+};
+
+/// Pauses and resumes the game on [`Action::PAUSE`].
+///
+/// While paused the virtual clock is stopped, so no simulation tick runs and no time
+/// builds up to be caught up on when resuming, and the physics pipeline is turned off.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            toggle_pause_system
+                .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+        )
+        .add_systems(OnEnter(GameState::Paused), pause)
+        .add_systems(OnExit(GameState::Paused), resume);
+    }
+}
+
+fn toggle_pause_system(
+    players: Query<&ActionState<Action>, With<Player>>,
+    rebinding: Option<Res<Rebinding>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // The pause key also cancels a rebinding, possibly earlier in this frame
+    if rebinding.is_some_and(|rebinding| rebinding.action.is_some() || rebinding.is_changed()) {
+        return;
+    }
+    if !players
+        .iter()
+        .any(|action_state| action_state.just_pressed(&Action::PAUSE))
+    {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+fn pause(mut time: ResMut<Time<Virtual>>, mut rapier_config: ResMut<RapierConfiguration>) {
+    time.pause();
+    rapier_config.physics_pipeline_active = false;
+}
+
+fn resume(mut time: ResMut<Time<Virtual>>, mut rapier_config: ResMut<RapierConfiguration>) {
+    time.unpause();
+    rapier_config.physics_pipeline_active = true;
+}
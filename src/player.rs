@@ -0,0 +1,304 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bindings::KeyBindings,
+    block::{BlockBundle, BlockShape},
+    configure_game_sets,
+    movement::{MovementConfig, MovementModel, PlayerMotion},
+    simulation::InterpolatedTransform,
+    GameSet,
+};
+
+/// Moves and rotates the players from their [`ActionState<Action>`].
+///
+/// Reading the actions from input devices is left to the app, by adding an
+/// [`InputManagerPlugin<Action>`].
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<LocalPlayers>()
+            .add_systems(FixedUpdate, move_player_system.in_set(GameSet::Movement));
+    }
+}
+
+pub const PADDLE_SIZE: Vec2 = Vec2::new(120.0, 20.0);
+
+pub const MAX_PLAYERS: usize = 4;
+
+/// Color of each player slot.
+pub const PLAYER_COLORS: [Color; MAX_PLAYERS] = [
+    Color::srgb(0.0, 0.8, 0.8),
+    Color::srgb(1.0, 0.55, 0.1),
+    Color::srgb(0.7, 0.4, 1.0),
+    Color::srgb(1.0, 0.9, 0.2),
+];
+
+#[derive(Component)]
+pub struct Player;
+
+/// Slot of a local player, from 0 to [`MAX_PLAYERS`] excluded.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PlayerId(pub u8);
+
+impl PlayerId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn color(self) -> Color {
+        PLAYER_COLORS[self.index() % MAX_PLAYERS]
+    }
+
+    /// A lighter shade of the player color, for the balls they serve.
+    pub fn ball_color(self) -> Color {
+        self.color().mix(&Color::WHITE, 0.5)
+    }
+}
+
+/// Points scored by a player in the current level.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct PlayerScore(pub u32);
+
+/// Number of players sharing the keyboard and gamepads, between 1 and [`MAX_PLAYERS`].
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LocalPlayers(u8);
+
+impl LocalPlayers {
+    /// Clamps `count` to the supported number of players.
+    pub fn new(count: usize) -> Self {
+        Self(count.clamp(1, MAX_PLAYERS) as u8)
+    }
+
+    pub fn count(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn ids(self) -> impl Iterator<Item = PlayerId> {
+        (0..self.0).map(PlayerId)
+    }
+}
+
+impl Default for LocalPlayers {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(
+    Actionlike,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Debug,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
+pub enum Action {
+    LEFT,
+    RIGHT,
+    UP,
+    DOWN,
+    RLEFT,
+    RRIGHT,
+    /// Analog movement, the deflection scales the speed.
+    #[actionlike(DualAxis)]
+    MOVE,
+    /// Analog rotation, positive values turn clockwise like [`Action::RRIGHT`].
+    #[actionlike(Axis)]
+    ROTATE,
+    /// Pauses or resumes the game, shared by every player and not rebindable.
+    PAUSE,
+}
+
+impl Action {
+    /// The actions triggered by buttons, in display order.
+    pub const BUTTONS: [Action; 6] = [
+        Action::LEFT,
+        Action::RIGHT,
+        Action::UP,
+        Action::DOWN,
+        Action::RLEFT,
+        Action::RRIGHT,
+    ];
+}
+
+/// The default keyboard bindings of `player`.
+pub fn player_input_map(player: PlayerId) -> InputMap<Action> {
+    KeyBindings::default().input_map(player, None)
+}
+
+/// The player paddle, a kinematic body driven by a character controller so Rapier
+/// resolves its movement against the obstacles.
+#[derive(Bundle)]
+pub struct PlayerBundle {
+    pub player: Player,
+    pub id: PlayerId,
+    pub score: PlayerScore,
+    pub block: BlockBundle,
+    pub body: RigidBody,
+    pub controller: KinematicCharacterController,
+    pub movement_model: MovementModel,
+    pub motion: PlayerMotion,
+    pub input: InputManagerBundle<Action>,
+    pub interpolated: InterpolatedTransform,
+}
+
+impl PlayerBundle {
+    pub fn new(
+        id: PlayerId,
+        position: Vec2,
+        movement_model: MovementModel,
+        material: Handle<ColorMaterial>,
+        meshes: &mut Assets<Mesh>,
+    ) -> Self {
+        Self {
+            player: Player,
+            id,
+            score: PlayerScore::default(),
+            block: BlockBundle::new(
+                BlockShape::new(PADDLE_SIZE),
+                material,
+                Transform::from_translation(position.extend(0.0)),
+                meshes,
+            ),
+            body: RigidBody::KinematicPositionBased,
+            controller: KinematicCharacterController {
+                // Top-down movement: there is no ground to snap to or slope to climb
+                up: Vec2::Y,
+                snap_to_ground: None,
+                autostep: None,
+                slide: true,
+                ..default()
+            },
+            movement_model,
+            motion: PlayerMotion::default(),
+            input: InputManagerBundle::<Action> {
+                input_map: player_input_map(id),
+                ..default()
+            },
+            interpolated: InterpolatedTransform::default(),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_player_system(
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &Collider,
+            &mut KinematicCharacterController,
+            Option<&KinematicCharacterControllerOutput>,
+            &MovementModel,
+            &mut PlayerMotion,
+            &ActionState<Action>,
+        ),
+        With<Player>,
+    >,
+    rapier_context: Res<RapierContext>,
+    movement_config: Res<MovementConfig>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+
+    for (
+        entity,
+        mut transform,
+        collider,
+        mut controller,
+        output,
+        model,
+        mut motion,
+        action_state,
+    ) in query.iter_mut()
+    {
+        // Whatever the obstacles stopped of last frame's movement is lost momentum
+        if let Some(output) = output {
+            if output
+                .desired_translation
+                .distance(output.effective_translation)
+                > f32::EPSILON
+            {
+                motion.velocity = output.effective_translation / dt;
+            }
+        }
+
+        let mut direction = Vec2::ZERO;
+
+        if action_state.pressed(&Action::UP) {
+            direction.y += 1.0;
+        }
+        if action_state.pressed(&Action::DOWN) {
+            direction.y -= 1.0;
+        }
+        if action_state.pressed(&Action::LEFT) {
+            direction.x -= 1.0;
+        }
+        if action_state.pressed(&Action::RIGHT) {
+            direction.x += 1.0;
+        }
+
+        // Normalize direction vector to avoid faster diagonal movement
+        direction = direction.normalize_or_zero();
+
+        // Analog input adds to the keys, a partially deflected stick moves slower
+        direction =
+            (direction + action_state.clamped_axis_pair(&Action::MOVE)).clamp_length_max(1.0);
+
+        // Rotation handling
+        let mut rotation_input = 0.0;
+        if action_state.pressed(&Action::RLEFT) {
+            rotation_input += 1.0;
+        }
+        if action_state.pressed(&Action::RRIGHT) {
+            rotation_input -= 1.0;
+        }
+        rotation_input =
+            (rotation_input - action_state.clamped_value(&Action::ROTATE)).clamp(-1.0, 1.0);
+
+        motion.steer(*model, direction, rotation_input, &movement_config, dt);
+
+        // Only rotate if the rotated collider would not overlap an obstacle,
+        // the character controller can only resolve translations
+        let angle = motion.angular_velocity * dt;
+        if angle != 0.0 {
+            let rotation = transform.rotation * Quat::from_rotation_z(angle);
+            let (_, _, rotation_z) = rotation.to_euler(EulerRot::XYZ);
+            let filter = QueryFilter::default()
+                .exclude_collider(entity)
+                .exclude_sensors();
+            let blocked = rapier_context
+                .intersection_with_shape(
+                    transform.translation.truncate(),
+                    rotation_z,
+                    collider,
+                    filter,
+                )
+                .is_some();
+
+            if blocked {
+                motion.angular_velocity = 0.0;
+            } else {
+                transform.rotation = rotation;
+            }
+        }
+
+        // The character controller collides and slides along the obstacles and walls
+        controller.translation = Some(motion.velocity * dt);
+    }
+}
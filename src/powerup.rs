@@ -0,0 +1,577 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::{
+    ecs::entity::EntityHashSet,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    arena::PlayField,
+    ball::{
+        deflect_balls_system, Ball, BallBundle, BallSpeed, ExtraBall, LastHitBy, BALL_RADIUS,
+        BALL_SPEED,
+    },
+    block::BlockShape,
+    collision::{BallHitPlayer, LaserHitBrick, PlayerCollectedPowerUp},
+    configure_game_sets,
+    level::LevelEntity,
+    obstacle::BrickDestroyed,
+    player::{Player, PADDLE_SIZE},
+    rng::GameRng,
+    score::Lives,
+    simulation::InterpolatedTransform,
+    state::InLevel,
+    GameSet,
+};
+
+/// Drops power-ups from some of the destroyed bricks, and applies the effects of those
+/// the paddles catch.
+///
+/// Timed effects are shared by the whole team: catching one that is already active
+/// extends it, up to [`MAX_EFFECT_STACKS`] times its duration, and everything it
+/// changed goes back to normal once it runs out.
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<ActiveEffects>()
+            .init_resource::<LaserCooldown>()
+            .init_resource::<GameRng>()
+            .add_systems(OnEnter(InLevel), (reset_effects, spawn_effects_hud))
+            .add_systems(
+                FixedUpdate,
+                (
+                    (fall_power_ups_system, move_lasers_system).in_set(GameSet::Movement),
+                    (
+                        collect_power_ups_system,
+                        // Holds the ball still rather than sending it off the paddle
+                        stick_balls_system.after(deflect_balls_system),
+                        despawn_lasers_system,
+                    )
+                        .in_set(GameSet::Collisions),
+                    (
+                        drop_power_ups_system,
+                        tick_effects_system,
+                        apply_effects_system,
+                        carry_stuck_balls_system,
+                        fire_lasers_system,
+                    )
+                        .chain()
+                        .in_set(GameSet::Rules),
+                ),
+            )
+            .add_systems(Update, update_effects_hud_system.run_if(in_state(InLevel)));
+    }
+}
+
+/// Probability for a destroyed brick to drop a power-up.
+pub const POWER_UP_DROP_CHANCE: f64 = 0.15;
+pub const POWER_UP_RADIUS: f32 = 12.0;
+pub const POWER_UP_FALL_SPEED: f32 = 150.0;
+/// How many times its duration a timed effect can be extended to.
+pub const MAX_EFFECT_STACKS: u32 = 3;
+const WIDE_PADDLE_SCALE: f32 = 1.5;
+const SLOW_BALL_SCALE: f32 = 0.6;
+/// Angle between the balls split off by a multi-ball and the original one.
+const MULTI_BALL_SPREAD: f32 = std::f32::consts::FRAC_PI_6;
+/// Multi-ball stops adding balls past this many.
+const MAX_BALLS: usize = 16;
+/// Time between two shots of a paddle while the laser is active.
+const LASER_INTERVAL: Duration = Duration::from_millis(400);
+const LASER_SPEED: f32 = 800.0;
+const LASER_SIZE: Vec2 = Vec2::new(4.0, 16.0);
+const LASER_COLOR: Color = Color::srgb(1.0, 0.3, 0.3);
+/// Time a sticky paddle holds a ball before launching it.
+const STICKY_HOLD: Duration = Duration::from_secs(1);
+
+/// What a power-up does once caught.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PowerUpKind {
+    WidePaddle,
+    MultiBall,
+    SlowBall,
+    Laser,
+    Sticky,
+    ExtraLife,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::WidePaddle,
+        PowerUpKind::MultiBall,
+        PowerUpKind::SlowBall,
+        PowerUpKind::Laser,
+        PowerUpKind::Sticky,
+        PowerUpKind::ExtraLife,
+    ];
+
+    /// How long the effect lasts, `None` for the ones applied once when caught.
+    pub fn duration(self) -> Option<Duration> {
+        match self {
+            PowerUpKind::WidePaddle | PowerUpKind::Sticky => Some(Duration::from_secs(15)),
+            PowerUpKind::SlowBall | PowerUpKind::Laser => Some(Duration::from_secs(10)),
+            PowerUpKind::MultiBall | PowerUpKind::ExtraLife => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::WidePaddle => "Wide paddle",
+            PowerUpKind::MultiBall => "Multi-ball",
+            PowerUpKind::SlowBall => "Slow ball",
+            PowerUpKind::Laser => "Laser",
+            PowerUpKind::Sticky => "Sticky paddle",
+            PowerUpKind::ExtraLife => "Extra life",
+        }
+    }
+
+    /// Letter shown on the falling power-up.
+    fn symbol(self) -> &'static str {
+        match self {
+            PowerUpKind::WidePaddle => "W",
+            PowerUpKind::MultiBall => "M",
+            PowerUpKind::SlowBall => "S",
+            PowerUpKind::Laser => "L",
+            PowerUpKind::Sticky => "G",
+            PowerUpKind::ExtraLife => "+",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            PowerUpKind::WidePaddle => Color::srgb(0.3, 0.7, 1.0),
+            PowerUpKind::MultiBall => Color::srgb(1.0, 0.6, 0.2),
+            PowerUpKind::SlowBall => Color::srgb(0.5, 0.9, 0.5),
+            PowerUpKind::Laser => LASER_COLOR,
+            PowerUpKind::Sticky => Color::srgb(0.9, 0.8, 0.3),
+            PowerUpKind::ExtraLife => Color::srgb(0.9, 0.4, 0.8),
+        }
+    }
+}
+
+/// A power-up falling from a destroyed brick.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PowerUp(pub PowerUpKind);
+
+/// A falling power-up, a kinematic sensor only reporting the paddle catching it.
+#[derive(Bundle)]
+pub struct PowerUpBundle {
+    pub power_up: PowerUp,
+    pub mesh: MaterialMesh2dBundle<ColorMaterial>,
+    pub body: RigidBody,
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub active_events: ActiveEvents,
+    pub active_collision_types: ActiveCollisionTypes,
+    pub interpolated: InterpolatedTransform,
+}
+
+impl PowerUpBundle {
+    pub fn new(
+        kind: PowerUpKind,
+        position: Vec2,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Self {
+        Self {
+            power_up: PowerUp(kind),
+            mesh: MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(POWER_UP_RADIUS))),
+                material: materials.add(kind.color()),
+                transform: Transform::from_translation(position.extend(0.5)),
+                ..default()
+            },
+            body: RigidBody::KinematicPositionBased,
+            collider: Collider::ball(POWER_UP_RADIUS),
+            sensor: Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            // The paddles are kinematic too
+            active_collision_types: ActiveCollisionTypes::default()
+                | ActiveCollisionTypes::KINEMATIC_KINEMATIC,
+            interpolated: InterpolatedTransform::default(),
+        }
+    }
+}
+
+/// A shot fired by a paddle with the laser effect, damaging the first brick it reaches.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Laser {
+    /// The player who fired it, credited for the bricks it destroys.
+    pub player: Entity,
+    pub velocity: Vec2,
+}
+
+/// A ball held by a sticky paddle, at `offset` from its center in the paddle's frame.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Stuck {
+    pub player: Entity,
+    pub offset: Vec2,
+    pub release_in: Duration,
+}
+
+/// Time left of every active timed effect.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ActiveEffects {
+    pub remaining: BTreeMap<PowerUpKind, Duration>,
+}
+
+impl ActiveEffects {
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.remaining.contains_key(&kind)
+    }
+
+    /// Starts the effect of `kind`, or extends it if already active.
+    pub fn add(&mut self, kind: PowerUpKind) {
+        let Some(duration) = kind.duration() else {
+            return;
+        };
+
+        let remaining = self.remaining.entry(kind).or_default();
+        *remaining = (*remaining + duration).min(duration * MAX_EFFECT_STACKS);
+    }
+}
+
+/// Time until the paddles fire their next laser shots.
+#[derive(Resource, Default)]
+struct LaserCooldown(Duration);
+
+fn reset_effects(mut effects: ResMut<ActiveEffects>, mut cooldown: ResMut<LaserCooldown>) {
+    *effects = ActiveEffects::default();
+    *cooldown = LaserCooldown::default();
+}
+
+fn drop_power_ups_system(
+    mut commands: Commands,
+    mut destroyed: EventReader<BrickDestroyed>,
+    mut rng: ResMut<GameRng>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in destroyed.read() {
+        if !rng.gen_bool(POWER_UP_DROP_CHANCE) {
+            continue;
+        }
+
+        let kind = PowerUpKind::ALL[rng.gen_range(0..PowerUpKind::ALL.len())];
+        commands
+            .spawn((
+                PowerUpBundle::new(kind, event.position, &mut meshes, &mut materials),
+                LevelEntity,
+            ))
+            .with_children(|power_up| {
+                power_up.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        kind.symbol(),
+                        TextStyle {
+                            font_size: 18.0,
+                            color: Color::BLACK,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                    ..default()
+                });
+            });
+    }
+}
+
+fn fall_power_ups_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    play_field: Res<PlayField>,
+    mut power_ups: Query<(Entity, &mut Transform), With<PowerUp>>,
+) {
+    let bottom = -play_field.half_size().y - POWER_UP_RADIUS;
+    for (power_up, mut transform) in power_ups.iter_mut() {
+        transform.translation.y -= POWER_UP_FALL_SPEED * time.delta_seconds();
+        if transform.translation.y < bottom {
+            commands.entity(power_up).despawn_recursive();
+        }
+    }
+}
+
+fn move_lasers_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    play_field: Res<PlayField>,
+    mut lasers: Query<(Entity, &mut Transform, &Laser)>,
+) {
+    for (laser, mut transform, Laser { velocity, .. }) in lasers.iter_mut() {
+        transform.translation += (*velocity * time.delta_seconds()).extend(0.0);
+        if !play_field.contains(transform.translation.truncate()) {
+            commands.entity(laser).despawn_recursive();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn collect_power_ups_system(
+    mut commands: Commands,
+    mut collected: EventReader<PlayerCollectedPowerUp>,
+    power_ups: Query<&PowerUp>,
+    balls: Query<(&Transform, &Velocity, &LastHitBy, &Handle<ColorMaterial>), With<Ball>>,
+    mut effects: ResMut<ActiveEffects>,
+    mut lives: ResMut<Lives>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut caught: Local<Vec<Entity>>,
+) {
+    // Two paddles may reach the same power-up during a tick, only the first one gets it
+    caught.clear();
+
+    for event in collected.read() {
+        let Ok(&PowerUp(kind)) = power_ups.get(event.power_up) else {
+            continue;
+        };
+        if caught.contains(&event.power_up) {
+            continue;
+        }
+        caught.push(event.power_up);
+        commands.entity(event.power_up).despawn_recursive();
+        info!("Caught a {} power-up", kind.name());
+
+        match kind {
+            PowerUpKind::MultiBall => {
+                // Each ball in play splits in three, a held one as if launched upwards
+                let mut count = balls.iter().count();
+                for (transform, velocity, last_hit_by, material) in balls.iter() {
+                    let direction = velocity.linvel.try_normalize().unwrap_or(Vec2::Y);
+                    for angle in [-MULTI_BALL_SPREAD, MULTI_BALL_SPREAD] {
+                        if count >= MAX_BALLS {
+                            break;
+                        }
+                        count += 1;
+
+                        // Ahead of the ball it splits from, already on its way
+                        let split_direction = Vec2::from_angle(angle).rotate(direction);
+                        commands.spawn((
+                            BallBundle {
+                                last_hit_by: *last_hit_by,
+                                velocity: Velocity::linear(split_direction * BALL_SPEED),
+                                ..BallBundle::new(
+                                    transform.translation.truncate()
+                                        + split_direction * 2.0 * BALL_RADIUS,
+                                    material.clone(),
+                                    &mut meshes,
+                                )
+                            },
+                            ExtraBall,
+                            LevelEntity,
+                        ));
+                    }
+                }
+            }
+            PowerUpKind::ExtraLife => lives.0 += 1,
+            _ => effects.add(kind),
+        }
+    }
+}
+
+/// Holds the balls touching a sticky paddle where they hit it.
+#[allow(clippy::type_complexity)]
+fn stick_balls_system(
+    mut commands: Commands,
+    mut hits: EventReader<BallHitPlayer>,
+    effects: Res<ActiveEffects>,
+    mut balls: Query<(&Transform, &mut Velocity), (With<Ball>, Without<Stuck>)>,
+    players: Query<&Transform, With<Player>>,
+) {
+    if !effects.is_active(PowerUpKind::Sticky) {
+        hits.clear();
+        return;
+    }
+
+    for hit in hits.read() {
+        let (Ok((ball_transform, mut velocity)), Ok(player_transform)) =
+            (balls.get_mut(hit.ball), players.get(hit.player))
+        else {
+            continue;
+        };
+
+        let offset = player_transform.rotation.inverse()
+            * (ball_transform.translation - player_transform.translation);
+        velocity.linvel = Vec2::ZERO;
+        commands.entity(hit.ball).insert(Stuck {
+            player: hit.player,
+            offset: offset.truncate(),
+            release_in: STICKY_HOLD,
+        });
+    }
+}
+
+fn despawn_lasers_system(mut commands: Commands, mut hits: EventReader<LaserHitBrick>) {
+    // A shot reaching two bricks at once damages both
+    let lasers: EntityHashSet = hits.read().map(|hit| hit.laser).collect();
+    for laser in lasers {
+        commands.entity(laser).despawn_recursive();
+    }
+}
+
+fn tick_effects_system(time: Res<Time>, mut effects: ResMut<ActiveEffects>) {
+    effects.remaining.retain(|kind, remaining| {
+        *remaining = remaining.saturating_sub(time.delta());
+        if remaining.is_zero() {
+            info!("{} wore off", kind.name());
+        }
+        !remaining.is_zero()
+    });
+}
+
+/// Sizes the paddles and sets the speed of the balls for the active effects, putting
+/// them back once the effects run out.
+fn apply_effects_system(
+    effects: Res<ActiveEffects>,
+    mut ball_speed: ResMut<BallSpeed>,
+    mut paddles: Query<&mut BlockShape, With<Player>>,
+) {
+    let speed = if effects.is_active(PowerUpKind::SlowBall) {
+        BALL_SPEED * SLOW_BALL_SCALE
+    } else {
+        BALL_SPEED
+    };
+    if ball_speed.0 != speed {
+        ball_speed.0 = speed;
+    }
+
+    let paddle_size = if effects.is_active(PowerUpKind::WidePaddle) {
+        PADDLE_SIZE * Vec2::new(WIDE_PADDLE_SCALE, 1.0)
+    } else {
+        PADDLE_SIZE
+    };
+    for mut shape in paddles.iter_mut() {
+        if shape.size != paddle_size {
+            shape.size = paddle_size;
+        }
+    }
+}
+
+/// Moves the held balls along with their paddle, and launches them along the paddle's
+/// up direction once held long enough.
+fn carry_stuck_balls_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    ball_speed: Res<BallSpeed>,
+    mut balls: Query<(Entity, &mut Transform, &mut Velocity, &mut Stuck), With<Ball>>,
+    players: Query<&Transform, (With<Player>, Without<Ball>)>,
+) {
+    for (ball, mut transform, mut velocity, mut stuck) in balls.iter_mut() {
+        let Ok(player_transform) = players.get(stuck.player) else {
+            commands.entity(ball).remove::<Stuck>();
+            continue;
+        };
+
+        let offset = player_transform.rotation * stuck.offset.extend(0.0);
+        transform.translation = player_transform.translation + offset;
+        velocity.linvel = Vec2::ZERO;
+
+        stuck.release_in = stuck.release_in.saturating_sub(time.delta());
+        if stuck.release_in.is_zero() {
+            velocity.linvel = (player_transform.rotation * Vec3::Y).truncate() * ball_speed.0;
+            commands.entity(ball).remove::<Stuck>();
+        }
+    }
+}
+
+fn fire_lasers_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    effects: Res<ActiveEffects>,
+    mut cooldown: ResMut<LaserCooldown>,
+    players: Query<(Entity, &Transform, &BlockShape), With<Player>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !effects.is_active(PowerUpKind::Laser) {
+        cooldown.0 = Duration::ZERO;
+        return;
+    }
+
+    cooldown.0 = cooldown.0.saturating_sub(time.delta());
+    if !cooldown.0.is_zero() {
+        return;
+    }
+    cooldown.0 = LASER_INTERVAL;
+
+    let mesh = meshes.add(Rectangle::from_size(LASER_SIZE));
+    let material = materials.add(LASER_COLOR);
+    for (player, transform, shape) in players.iter() {
+        // One shot from each end of the paddle
+        let half_extents = shape.half_extents();
+        for side in [-1.0, 1.0] {
+            let muzzle = Vec3::new(
+                side * (half_extents.x - LASER_SIZE.x),
+                half_extents.y + LASER_SIZE.y / 2.0,
+                0.0,
+            );
+            commands.spawn((
+                Laser {
+                    player,
+                    velocity: (transform.rotation * Vec3::Y).truncate() * LASER_SPEED,
+                },
+                MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(mesh.clone()),
+                    material: material.clone(),
+                    transform: Transform {
+                        translation: transform.translation + transform.rotation * muzzle,
+                        rotation: transform.rotation,
+                        ..default()
+                    },
+                    ..default()
+                },
+                RigidBody::KinematicPositionBased,
+                Collider::cuboid(LASER_SIZE.x / 2.0, LASER_SIZE.y / 2.0),
+                Sensor,
+                ActiveEvents::COLLISION_EVENTS,
+                ActiveCollisionTypes::default()
+                    | ActiveCollisionTypes::KINEMATIC_STATIC
+                    | ActiveCollisionTypes::KINEMATIC_KINEMATIC,
+                InterpolatedTransform::default(),
+                LevelEntity,
+            ));
+        }
+    }
+}
+
+#[derive(Component)]
+struct EffectsText;
+
+fn spawn_effects_hud(mut commands: Commands) {
+    commands.spawn((
+        EffectsText,
+        StateScoped(InLevel),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 22.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(44.0),
+            left: Val::Px(16.0),
+            ..default()
+        }),
+    ));
+}
+
+fn update_effects_hud_system(
+    effects: Res<ActiveEffects>,
+    mut texts: Query<&mut Text, With<EffectsText>>,
+) {
+    if !effects.is_changed() {
+        return;
+    }
+
+    let lines: Vec<String> = effects
+        .remaining
+        .iter()
+        .map(|(kind, remaining)| format!("{} {}s", kind.name(), remaining.as_secs_f32().ceil()))
+        .collect();
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use leafwing_input_manager::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configure_game_sets,
+    level::CurrentLevel,
+    player::{Action, LocalPlayers, Player, PlayerId},
+    rng::GameRng,
+    simulation::TickRate,
+    state::{GameState, InLevel},
+    storage, GameSet,
+};
+
+/// Records the inputs of every player to a file, or replays a recorded file in place
+/// of the input devices.
+///
+/// A recording starts on the tick the level spawns and stores the action states of
+/// every tick from there until the level ends, along with the seed of the [`GameRng`]
+/// and the tick rate. Replaying it with the same build and assets gives back the exact
+/// same run, and exits the app once it is over.
+///
+/// Leaving a level, even to restart it, also stops the recording, and reseeds the
+/// [`GameRng`] so the next level starts from a known seed again.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<ReplayMode>()
+            .init_resource::<GameRng>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                FixedUpdate,
+                (
+                    record_inputs_system.run_if(resource_exists::<Recording>),
+                    replay_inputs_system.run_if(resource_exists::<Replay>),
+                )
+                    .in_set(GameSet::Input),
+            )
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (finish_recording, finish_replay),
+            )
+            .add_systems(
+                OnEnter(GameState::LevelComplete),
+                (finish_recording, finish_replay),
+            )
+            .add_systems(OnExit(InLevel), (finish_recording, reseed_rng))
+            .add_systems(Last, save_recording_system);
+    }
+}
+
+/// Whether the inputs of this run are recorded to, or replayed from, a file.
+#[derive(Resource, Clone, Debug, Default)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    /// Record the run, the file is written when the level ends or the app exits.
+    Record(PathBuf),
+    /// Replay a recorded run, the app exits once it ends.
+    Replay(PathBuf),
+}
+
+/// The inputs of a player during one tick.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputSnapshot {
+    pub pressed: Vec<Action>,
+    pub movement: Vec2,
+    pub rotation: f32,
+}
+
+impl InputSnapshot {
+    pub fn capture(action_state: &ActionState<Action>) -> Self {
+        Self {
+            pressed: Action::BUTTONS
+                .into_iter()
+                .filter(|action| action_state.pressed(action))
+                .collect(),
+            movement: action_state.axis_pair(&Action::MOVE),
+            rotation: action_state.value(&Action::ROTATE),
+        }
+    }
+
+    /// Overwrites `action_state` with the recorded inputs.
+    pub fn apply(&self, action_state: &mut ActionState<Action>) {
+        for action in Action::BUTTONS {
+            if self.pressed.contains(&action) {
+                action_state.press(&action);
+            } else {
+                action_state.release(&action);
+            }
+        }
+        action_state.set_axis_pair(&Action::MOVE, self.movement);
+        action_state.set_value(&Action::ROTATE, self.rotation);
+    }
+}
+
+/// A recorded run: what it takes to start it again, then the inputs of every tick,
+/// one snapshot per player in [`PlayerId`] order.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub timestep: Duration,
+    pub players: u8,
+    pub ticks: Vec<Vec<InputSnapshot>>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, timestep: Duration, players: LocalPlayers) -> Self {
+        Self {
+            seed,
+            timestep,
+            players: players.count() as u8,
+            ticks: Vec::new(),
+        }
+    }
+}
+
+/// A recording being replayed, and the next tick to replay.
+#[derive(Resource, Clone, Debug)]
+pub struct Replay {
+    pub recording: Recording,
+    pub tick: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, tick: 0 }
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mode: Res<ReplayMode>,
+    rng: Res<GameRng>,
+    local_players: Res<LocalPlayers>,
+    tick_rate: Res<TickRate>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    match &*mode {
+        ReplayMode::Off => {}
+        ReplayMode::Record(path) => {
+            info!("Recording inputs to {}", path.display());
+            commands.insert_resource(Recording::new(
+                rng.seed(),
+                tick_rate.timestep(),
+                *local_players,
+            ));
+        }
+        ReplayMode::Replay(path) => {
+            let recording = match storage::load::<Recording>(path) {
+                Ok(Some(recording)) => recording,
+                Ok(None) => {
+                    error!("Replay {} does not exist", path.display());
+                    return;
+                }
+                Err(error) => {
+                    error!("Could not load the replay: {error}");
+                    return;
+                }
+            };
+
+            info!(
+                "Replaying {} ticks from {}",
+                recording.ticks.len(),
+                path.display()
+            );
+            commands.insert_resource(GameRng::new(recording.seed));
+            commands.insert_resource(LocalPlayers::new(recording.players as usize));
+            commands.insert_resource(TickRate::from_timestep(recording.timestep));
+            commands.insert_resource(Replay::new(recording));
+            // The menus are not part of the recording
+            next_state.set(GameState::Loading);
+        }
+    }
+}
+
+fn record_inputs_system(
+    current_level: Res<CurrentLevel>,
+    mut recording: ResMut<Recording>,
+    players: Query<(&PlayerId, &ActionState<Action>), With<Player>>,
+) {
+    if !current_level.spawned {
+        return;
+    }
+
+    let mut snapshots: Vec<_> = players.iter().collect();
+    snapshots.sort_by_key(|(id, _)| **id);
+    recording.ticks.push(
+        snapshots
+            .into_iter()
+            .map(|(_, action_state)| InputSnapshot::capture(action_state))
+            .collect(),
+    );
+}
+
+fn replay_inputs_system(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    mut replay: ResMut<Replay>,
+    mut players: Query<(&PlayerId, &mut ActionState<Action>), With<Player>>,
+    mut exits: EventWriter<AppExit>,
+) {
+    if !current_level.spawned {
+        return;
+    }
+
+    let Some(snapshots) = replay.recording.ticks.get(replay.tick) else {
+        info!("Replay finished after {} ticks", replay.tick);
+        commands.remove_resource::<Replay>();
+        exits.send(AppExit::Success);
+        return;
+    };
+
+    for (id, mut action_state) in players.iter_mut() {
+        if let Some(snapshot) = snapshots.get(id.index()) {
+            snapshot.apply(&mut action_state);
+        }
+    }
+    replay.tick += 1;
+}
+
+/// Ends a replay with its level, the simulation stops there so the remaining ticks,
+/// if any, would never be replayed.
+fn finish_replay(
+    mut commands: Commands,
+    replay: Option<Res<Replay>>,
+    mut exits: EventWriter<AppExit>,
+) {
+    let Some(replay) = replay else {
+        return;
+    };
+
+    info!("Replay finished with the level after {} ticks", replay.tick);
+    commands.remove_resource::<Replay>();
+    exits.send(AppExit::Success);
+}
+
+/// Saves the recording once its level is over or left, and stops recording: the menus and the
+/// levels after it are not part of it.
+fn finish_recording(
+    mut commands: Commands,
+    mode: Res<ReplayMode>,
+    recording: Option<Res<Recording>>,
+) {
+    let (ReplayMode::Record(path), Some(recording)) = (&*mode, recording) else {
+        return;
+    };
+
+    save_recording(path, &recording);
+    commands.remove_resource::<Recording>();
+}
+
+/// Starts the next level from a fresh seed, drawn from the current one so seeded runs
+/// stay reproducible across levels and restarts.
+fn reseed_rng(mut rng: ResMut<GameRng>) {
+    let seed = rng.next_u64();
+    *rng = GameRng::new(seed);
+}
+
+/// Saves the recording of a level left unfinished when the app exits.
+fn save_recording_system(
+    mut exits: EventReader<AppExit>,
+    mode: Res<ReplayMode>,
+    recording: Option<Res<Recording>>,
+) {
+    if exits.read().next().is_none() {
+        return;
+    }
+    let (ReplayMode::Record(path), Some(recording)) = (&*mode, recording) else {
+        return;
+    };
+
+    save_recording(path, &recording);
+}
+
+fn save_recording(path: &Path, recording: &Recording) {
+    match storage::save(path, recording) {
+        Ok(()) => info!(
+            "Saved {} ticks of inputs to {}",
+            recording.ticks.len(),
+            path.display()
+        ),
+        Err(error) => error!("Could not save the recording: {error}"),
+    }
+}
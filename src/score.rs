@@ -0,0 +1,280 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    ball::{Ball, BallBundle, BallLost, ExtraBall, LastHitBy, BALL_SERVE_OFFSET},
+    collision::BallHitPlayer,
+    configure_game_sets,
+    level::{CurrentLevel, Level, LevelEntity},
+    obstacle::BrickDestroyed,
+    player::{LocalPlayers, Player, PlayerId, PlayerScore},
+    state::{GameState, InLevel},
+    GameSet,
+};
+
+/// Keeps the score and lives of the players and shows them in a HUD, along with the
+/// points each player scored.
+///
+/// Destroying bricks scores points, multiplied by the combo of bricks destroyed since a
+/// ball last touched a paddle. Losing a ball costs a life and serves a new one, the game
+/// is over once there are no lives left.
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        configure_game_sets(app);
+        app.init_resource::<Score>()
+            .init_resource::<Lives>()
+            .init_resource::<PlayTime>()
+            .add_systems(OnEnter(InLevel), (reset_score, spawn_hud))
+            .add_systems(
+                FixedUpdate,
+                (
+                    break_combo_system.in_set(GameSet::Collisions),
+                    (score_bricks_system, lose_ball_system, play_time_system)
+                        .in_set(GameSet::Rules),
+                ),
+            )
+            .add_systems(Update, update_hud_system.run_if(in_state(InLevel)));
+    }
+}
+
+/// Points scored for destroying a brick, before the combo multiplier.
+pub const BRICK_POINTS: u32 = 10;
+pub const STARTING_LIVES: u32 = 3;
+/// Number of bricks to destroy in a row to raise the multiplier by one.
+pub const COMBO_STEP: u32 = 3;
+pub const MAX_MULTIPLIER: u32 = 5;
+
+/// Points scored by all the players together in the current level, and the number of
+/// bricks destroyed since a ball last touched a paddle.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct Score {
+    pub points: u32,
+    pub combo: u32,
+}
+
+impl Score {
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.combo / COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+}
+
+/// Balls the players can still lose before the game is over.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Lives(pub u32);
+
+impl Default for Lives {
+    fn default() -> Self {
+        Self(STARTING_LIVES)
+    }
+}
+
+/// Time spent playing the current level, counted in ticks so it does not include pauses
+/// and is the same when replayed.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct PlayTime(pub Duration);
+
+fn reset_score(
+    mut score: ResMut<Score>,
+    mut lives: ResMut<Lives>,
+    mut play_time: ResMut<PlayTime>,
+) {
+    *score = Score::default();
+    *lives = Lives::default();
+    *play_time = PlayTime::default();
+}
+
+fn break_combo_system(mut hits: EventReader<BallHitPlayer>, mut score: ResMut<Score>) {
+    if hits.read().count() > 0 && score.combo > 0 {
+        score.combo = 0;
+    }
+}
+
+fn score_bricks_system(
+    mut destroyed: EventReader<BrickDestroyed>,
+    mut score: ResMut<Score>,
+    mut player_scores: Query<&mut PlayerScore>,
+) {
+    for event in destroyed.read() {
+        let points = BRICK_POINTS * score.multiplier();
+        score.points += points;
+        score.combo += 1;
+
+        if let Some(mut player_score) = event
+            .player
+            .and_then(|player| player_scores.get_mut(player).ok())
+        {
+            player_score.0 += points;
+        }
+    }
+}
+
+fn play_time_system(time: Res<Time>, mut play_time: ResMut<PlayTime>) {
+    play_time.0 += time.delta();
+}
+
+/// Takes a life for every lost ball, and serves a new one from the paddle of the
+/// player who lost it. Extra balls are just removed.
+#[allow(clippy::too_many_arguments)]
+fn lose_ball_system(
+    mut commands: Commands,
+    mut lost: EventReader<BallLost>,
+    balls: Query<(&LastHitBy, Has<ExtraBall>), With<Ball>>,
+    players: Query<(Entity, &PlayerId, &Transform), With<Player>>,
+    mut score: ResMut<Score>,
+    mut lives: ResMut<Lives>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in lost.read() {
+        let Ok((last_hit_by, extra)) = balls.get(event.ball) else {
+            continue;
+        };
+        commands.entity(event.ball).despawn_recursive();
+        if extra {
+            continue;
+        }
+
+        score.combo = 0;
+        lives.0 = lives.0.saturating_sub(1);
+        if lives.0 == 0 {
+            next_state.set(GameState::GameOver);
+            continue;
+        }
+
+        let server = last_hit_by
+            .0
+            .and_then(|player| players.get(player).ok())
+            .or_else(|| players.iter().min_by_key(|(_, id, _)| **id));
+        let Some((player, id, transform)) = server else {
+            continue;
+        };
+
+        commands.spawn((
+            BallBundle {
+                last_hit_by: LastHitBy(Some(player)),
+                ..BallBundle::new(
+                    transform.translation.truncate() + BALL_SERVE_OFFSET,
+                    materials.add(id.ball_color()),
+                    &mut meshes,
+                )
+            },
+            LevelEntity,
+        ));
+    }
+}
+
+/// Which value a HUD text shows.
+#[derive(Component, Clone, Copy)]
+enum HudText {
+    Score,
+    Multiplier,
+    PlayerScore(PlayerId),
+    Lives,
+}
+
+fn spawn_hud(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    local_players: Res<LocalPlayers>,
+) {
+    let text_style = TextStyle {
+        font_size: 28.0,
+        ..default()
+    };
+    let level_name = levels
+        .get(&current_level.handle)
+        .map_or_else(String::new, |level| level.name.clone());
+
+    commands
+        .spawn((
+            StateScoped(InLevel),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::axes(Val::Px(16.0), Val::Px(8.0)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|hud| {
+            hud.spawn(NodeBundle {
+                style: Style {
+                    column_gap: Val::Px(12.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|score| {
+                score.spawn((
+                    HudText::Score,
+                    TextBundle::from_section("", text_style.clone()),
+                ));
+                score.spawn((
+                    HudText::Multiplier,
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            color: Color::srgb(1.0, 0.8, 0.2),
+                            ..text_style.clone()
+                        },
+                    ),
+                ));
+                // The share of every player, in their color
+                for player in local_players.ids() {
+                    score.spawn((
+                        HudText::PlayerScore(player),
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                color: player.color(),
+                                ..text_style.clone()
+                            },
+                        ),
+                    ));
+                }
+            });
+            hud.spawn(TextBundle::from_section(level_name, text_style.clone()));
+            hud.spawn((
+                HudText::Lives,
+                TextBundle::from_section("", text_style.clone()),
+            ));
+        });
+}
+
+fn update_hud_system(
+    score: Res<Score>,
+    lives: Res<Lives>,
+    players: Query<(&PlayerId, Ref<PlayerScore>)>,
+    mut texts: Query<(Ref<HudText>, &mut Text)>,
+) {
+    let players_changed = players
+        .iter()
+        .any(|(_, player_score)| player_score.is_changed());
+
+    for (hud_text, mut text) in texts.iter_mut() {
+        if !score.is_changed() && !lives.is_changed() && !players_changed && !hud_text.is_added() {
+            continue;
+        }
+
+        text.sections[0].value = match *hud_text {
+            HudText::Score => format!("Score {}", score.points),
+            HudText::Multiplier => format!("x{}", score.multiplier()),
+            HudText::PlayerScore(player) => {
+                let points = players
+                    .iter()
+                    .find(|(id, _)| **id == player)
+                    .map_or(0, |(_, player_score)| player_score.0);
+                format!("P{} {points}", player.0 + 1)
+            }
+            HudText::Lives => format!("Lives {}", lives.0),
+        };
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use bevy::{app::RunFixedMainLoop, prelude::*, time::run_fixed_main_schedule};
+
+/// Runs the gameplay at the [`TickRate`] and smooths the rendering of moving entities
+/// between two ticks.
+///
+/// The ticks always see the exact transforms they produced: the interpolated ones are
+/// only written for rendering, after the last tick of the frame, and the simulated ones
+/// are put back before the next tick runs.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TickRate>()
+            .init_resource::<RenderInterpolation>()
+            .add_systems(
+                RunFixedMainLoop,
+                (
+                    (
+                        apply_tick_rate_system.run_if(resource_changed::<TickRate>),
+                        restore_tick_transform_system,
+                    )
+                        .before(run_fixed_main_schedule),
+                    interpolate_transform_system
+                        .run_if(|interpolation: Res<RenderInterpolation>| interpolation.0)
+                        .after(run_fixed_main_schedule),
+                ),
+            )
+            .add_systems(FixedPostUpdate, store_tick_transform_system);
+    }
+}
+
+pub const DEFAULT_TICK_RATE: f64 = 60.0;
+
+/// How many gameplay and physics ticks are simulated per second.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct TickRate {
+    timestep: Duration,
+}
+
+impl TickRate {
+    /// # Panics
+    ///
+    /// Panics if `hz` is not strictly positive.
+    pub fn from_hz(hz: f64) -> Self {
+        assert!(hz > 0.0, "the tick rate must be positive, got {hz}");
+        Self::from_timestep(Duration::from_secs_f64(1.0 / hz))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `timestep` is zero.
+    pub fn from_timestep(timestep: Duration) -> Self {
+        assert!(!timestep.is_zero(), "the tick duration must be positive");
+        Self { timestep }
+    }
+
+    /// Duration of a tick.
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    pub fn hz(&self) -> f64 {
+        1.0 / self.timestep.as_secs_f64()
+    }
+}
+
+impl Default for TickRate {
+    fn default() -> Self {
+        Self::from_hz(DEFAULT_TICK_RATE)
+    }
+}
+
+/// Whether [`InterpolatedTransform`] entities are rendered between ticks. When disabled,
+/// their transforms are the ones of the last tick.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct RenderInterpolation(pub bool);
+
+impl Default for RenderInterpolation {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Renders the entity between its transforms of the last two ticks instead of jumping
+/// from one to the next.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct InterpolatedTransform {
+    previous: Option<Transform>,
+    current: Option<Transform>,
+}
+
+fn apply_tick_rate_system(tick_rate: Res<TickRate>, mut time: ResMut<Time<Fixed>>) {
+    time.set_timestep(tick_rate.timestep());
+}
+
+fn restore_tick_transform_system(mut query: Query<(&mut Transform, &InterpolatedTransform)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        if let Some(current) = interpolated.current {
+            transform.set_if_neq(current);
+        }
+    }
+}
+
+fn store_tick_transform_system(mut query: Query<(&Transform, &mut InterpolatedTransform)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        // An entity spawned during this tick has nowhere to come from
+        interpolated.previous = Some(interpolated.current.unwrap_or(*transform));
+        interpolated.current = Some(*transform);
+    }
+}
+
+fn interpolate_transform_system(
+    time: Res<Time<Fixed>>,
+    mut query: Query<(&mut Transform, &InterpolatedTransform)>,
+) {
+    let overstep = time.overstep_fraction();
+    for (mut transform, interpolated) in query.iter_mut() {
+        let (Some(previous), Some(current)) = (interpolated.previous, interpolated.current) else {
+            continue;
+        };
+
+        transform.set_if_neq(Transform {
+            translation: previous.translation.lerp(current.translation, overstep),
+            rotation: previous.rotation.slerp(current.rotation, overstep),
+            scale: previous.scale.lerp(current.scale, overstep),
+        });
+    }
+}
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+
+/// Registers the [`GameState`] machine and despawns [`StateScoped`] entities when
+/// their state is left.
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .add_computed_state::<InLevel>()
+            .enable_state_scoped_entities::<GameState>()
+            .enable_state_scoped_entities::<InLevel>();
+    }
+}
+
+/// Where the game is at, from the menu to the end of a level.
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    /// The high score tables, reached from the menus.
+    Leaderboard,
+    /// Waiting for the current level and its config files before spawning it.
+    Loading,
+    /// The level is spawned and the simulation runs.
+    Playing,
+    Paused,
+    /// The players lost, the level stays on screen behind the game over screen.
+    GameOver,
+    /// Every brick is destroyed.
+    LevelComplete,
+}
+
+/// Active while a level is spawned, whether it is being played or not. Leaving it
+/// clears the level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InLevel;
+
+impl ComputedStates for InLevel {
+    type SourceStates = GameState;
+
+    fn compute(state: GameState) -> Option<Self> {
+        match state {
+            GameState::Playing
+            | GameState::Paused
+            | GameState::GameOver
+            | GameState::LevelComplete => Some(InLevel),
+            GameState::MainMenu | GameState::Leaderboard | GameState::Loading => None,
+        }
+    }
+}
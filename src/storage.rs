@@ -0,0 +1,104 @@
+//! Files the game keeps between launches, stored in the user's config directory.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+/// Overrides the directory the game stores its files in.
+pub const CONFIG_DIR_VAR: &str = "BLOCKBRACKER_CONFIG_DIR";
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("could not access {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("could not parse {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: ron::error::SpannedError,
+    },
+    #[error("could not serialize {path}: {source}")]
+    Serialize { path: PathBuf, source: ron::Error },
+}
+
+/// Directory the game stores its files in, `None` if no suitable location could be found.
+///
+/// Uses [`CONFIG_DIR_VAR`] if set, then the platform's usual config directory.
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(CONFIG_DIR_VAR) {
+        return Some(PathBuf::from(dir));
+    }
+
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| Path::new(&home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+
+    base.map(|base| base.join("blockbracker"))
+}
+
+/// Reads a RON file, `Ok(None)` if it does not exist.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, StorageError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(StorageError::Io {
+                path: path.to_owned(),
+                source,
+            })
+        }
+    };
+
+    ron::from_str(&text)
+        .map(Some)
+        .map_err(|source| StorageError::Parse {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+/// Reads the RON file `name` from the [`config_dir`], the defaults if there is no such
+/// file or no config directory. For settings the player edits by hand and the game only
+/// reads.
+pub fn load_or_default<T: DeserializeOwned + Default>(name: &str) -> Result<T, StorageError> {
+    let Some(path) = config_dir().map(|dir| dir.join(name)) else {
+        return Ok(T::default());
+    };
+
+    Ok(load(&path)?.unwrap_or_default())
+}
+
+/// Writes a RON file, creating its directory if needed.
+///
+/// The file is replaced in one step, so a crash while saving never leaves it half written.
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
+    let io_error = |source| StorageError::Io {
+        path: path.to_owned(),
+        source,
+    };
+
+    let text =
+        ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()).map_err(|source| {
+            StorageError::Serialize {
+                path: path.to_owned(),
+                source,
+            }
+        })?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(io_error)?;
+    }
+
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, text).map_err(io_error)?;
+    fs::rename(&temporary, path).map_err(io_error)
+}
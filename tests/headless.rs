@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use blockbracker::{
+    ball::Ball,
+    headless::headless_app,
+    level::CurrentLevel,
+    player::Player,
+    rng::GameRng,
+    score::{Lives, Score},
+    Action, GameState,
+};
+use leafwing_input_manager::prelude::*;
+
+const TICKS: usize = 500;
+
+fn timestep() -> Duration {
+    Duration::from_secs_f64(1.0 / 64.0)
+}
+
+/// Where the balls are and what the players scored after a run.
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    balls: Vec<Vec3>,
+    points: u32,
+    lives: u32,
+}
+
+fn seeded_app(seed: u64) -> App {
+    let mut app = headless_app(timestep());
+    app.insert_resource(GameRng::new(seed));
+    for _ in 0..1000 {
+        app.update();
+        if app.world().resource::<CurrentLevel>().spawned {
+            return app;
+        }
+    }
+    panic!("the level never spawned");
+}
+
+/// Runs `TICKS` ticks with the paddle held to the right.
+fn run(seed: u64) -> Outcome {
+    let mut app = seeded_app(seed);
+    let world = app.world_mut();
+    let mut players = world.query_filtered::<&mut ActionState<Action>, With<Player>>();
+    for mut action_state in players.iter_mut(world) {
+        action_state.press(&Action::RIGHT);
+    }
+
+    for _ in 0..TICKS {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut balls: Vec<_> = world
+        .query_filtered::<&Transform, With<Ball>>()
+        .iter(world)
+        .map(|transform| transform.translation)
+        .collect();
+    balls.sort_by(|a, b| a.to_array().partial_cmp(&b.to_array()).unwrap());
+
+    Outcome {
+        balls,
+        points: world.resource::<Score>().points,
+        lives: world.resource::<Lives>().0,
+    }
+}
+
+#[test]
+fn starts_the_level_without_the_menus() {
+    let app = seeded_app(1);
+    assert_eq!(
+        *app.world().resource::<State<GameState>>(),
+        GameState::Playing
+    );
+}
+
+#[test]
+fn every_update_runs_one_tick() {
+    let mut app = seeded_app(1);
+    let before = app.world().resource::<Time<Fixed>>().elapsed();
+    for _ in 0..10 {
+        app.update();
+    }
+    let after = app.world().resource::<Time<Fixed>>().elapsed();
+    assert_eq!(after - before, timestep() * 10);
+}
+
+#[test]
+fn runs_with_the_same_seed_end_the_same() {
+    let first = run(42);
+    assert!(!first.balls.is_empty());
+    assert!(first.balls.iter().all(|ball| ball.is_finite()));
+    // Long enough for the ball to break some bricks
+    assert!(first.points > 0);
+    assert_eq!(run(42), first);
+}
@@ -0,0 +1,1013 @@
+//! Every plugin on its own, on top of the minimal Bevy plugins and whatever resources
+//! and events it expects another plugin to provide.
+
+use std::{sync::Once, time::Duration};
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput, NativeKey},
+        ButtonState, InputPlugin,
+    },
+    prelude::*,
+    render::camera::ScalingMode,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+    window::PrimaryWindow,
+};
+use bevy_rapier2d::{prelude::*, rapier::geometry::CollisionEventFlags};
+use blockbracker::{
+    arena::{KillZone, PlayField, Wall},
+    ball::{Ball, BallLost, BallSpeed, LastHitBy, MIN_VERTICAL_SPEED},
+    bindings::{KeyBindings, Rebinding},
+    block::{BlockBundle, BlockShape},
+    camera::{CameraRig, CameraScaling, CameraSettings, MainCamera},
+    collision::{
+        BallHitBrick, BallHitPlayer, LaserHitBrick, PlayerCollectedPowerUp, PlayerHitObstacle,
+    },
+    highscore::{HighScores, InitialsEntry},
+    level::{CurrentLevel, Level},
+    menu::MenuButton,
+    movement::{MovementConfig, MovementModel, PlayerMotion},
+    obstacle::{BrickColor, BrickDestroyed, HitPoints, Indestructible, Obstacle},
+    overlay::{DisplayMode, CLICK_THROUGH_KEY},
+    player::{LocalPlayers, Player, PlayerBundle, PlayerId, PlayerScore},
+    powerup::{ActiveEffects, PowerUp, PowerUpKind},
+    replay::{Recording, ReplayMode},
+    rng::GameRng,
+    score::{Lives, PlayTime, Score, BRICK_POINTS, STARTING_LIVES},
+    simulation::TickRate,
+    state::InLevel,
+    storage, Action, ArenaPlugin, BallPlugin, BindingsPlugin, BlockPlugin, CameraPlugin,
+    CollisionPlugin, GameState, GameStatePlugin, HighScorePlugin, LevelPlugin, MenuPlugin,
+    MovementPlugin, ObstaclePlugin, OverlayPlugin, PausePlugin, PlayerPlugin, PowerUpPlugin,
+    ReplayPlugin, ScorePlugin, SimulationPlugin,
+};
+use leafwing_input_manager::prelude::*;
+
+/// Keeps the files the plugins read and write out of the user's config directory.
+fn isolate_config_dir() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("blockbracker-plugins-{}", std::process::id()));
+        std::env::set_var(storage::CONFIG_DIR_VAR, dir);
+    });
+}
+
+/// Makes every update run exactly one tick of the default tick rate.
+fn tick_every_update(app: &mut App) {
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ));
+}
+
+fn enter(app: &mut App, state: GameState) {
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(state);
+    app.update();
+    assert_eq!(*app.world().resource::<State<GameState>>(), state);
+}
+
+fn keyboard(app: &mut App, key_code: KeyCode, state: ButtonState) {
+    app.world_mut().send_event(KeyboardInput {
+        key_code,
+        logical_key: Key::Unidentified(NativeKey::Unidentified),
+        state,
+        window: Entity::PLACEHOLDER,
+    });
+}
+
+/// Presses `key` for one update.
+fn tap(app: &mut App, key: KeyCode) {
+    keyboard(app, key, ButtonState::Pressed);
+    app.update();
+    keyboard(app, key, ButtonState::Released);
+}
+
+/// The events of type `E` sent during the last two updates.
+fn events<E: Event + Clone>(app: &App) -> Vec<E> {
+    let events = app.world().resource::<Events<E>>();
+    events.get_reader().read(events).cloned().collect()
+}
+
+fn count<F: bevy::ecs::query::QueryFilter>(app: &mut App) -> usize {
+    let world = app.world_mut();
+    world.query_filtered::<(), F>().iter(world).count()
+}
+
+fn test_level(name: &str) -> Level {
+    Level {
+        name: name.to_string(),
+        player_spawn: (0.0, -250.0),
+        brick_size: (60.0, 20.0),
+        brick_spacing: (4.0, 4.0),
+        grid_top: 250.0,
+        brick_types: Default::default(),
+        layout: vec![".".to_string()],
+        movement: None,
+        catalogue: Default::default(),
+    }
+}
+
+/// A [`CurrentLevel`] already spawned, for the plugins reading it without a level plugin.
+fn current_level(app: &mut App, name: &str) {
+    let handle = app
+        .world_mut()
+        .resource_mut::<Assets<Level>>()
+        .add(test_level(name));
+    app.insert_resource(CurrentLevel {
+        handle,
+        spawned: true,
+    });
+}
+
+#[test]
+fn game_state_plugin_computes_in_level_and_clears_scoped_entities() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, GameStatePlugin));
+    app.update();
+    assert_eq!(
+        *app.world().resource::<State<GameState>>(),
+        GameState::MainMenu
+    );
+    assert!(app.world().get_resource::<State<InLevel>>().is_none());
+
+    enter(&mut app, GameState::Playing);
+    assert!(app.world().get_resource::<State<InLevel>>().is_some());
+    let scoped = app.world_mut().spawn(StateScoped(InLevel)).id();
+
+    // Pausing stays in the level
+    enter(&mut app, GameState::Paused);
+    assert!(app.world().get_entity(scoped).is_some());
+
+    enter(&mut app, GameState::MainMenu);
+    assert!(app.world().get_entity(scoped).is_none());
+}
+
+#[test]
+fn simulation_plugin_applies_the_tick_rate() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, SimulationPlugin));
+    app.update();
+    assert_eq!(*app.world().resource::<TickRate>(), TickRate::default());
+
+    app.insert_resource(TickRate::from_hz(30.0));
+    app.update();
+    assert_eq!(
+        app.world().resource::<Time<Fixed>>().timestep(),
+        TickRate::from_hz(30.0).timestep()
+    );
+}
+
+#[test]
+fn arena_plugin_encloses_the_play_field() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        ArenaPlugin,
+    ));
+    tick_every_update(&mut app);
+    app.update();
+    assert_eq!(count::<With<Wall>>(&mut app), 4);
+    assert_eq!(count::<With<KillZone>>(&mut app), 1);
+    assert!(app.world().get_resource::<PlayField>().is_some());
+
+    // The physics step follows the tick rate
+    let timestep = Duration::from_secs_f64(1.0 / 30.0);
+    app.world_mut()
+        .resource_mut::<Time<Fixed>>()
+        .set_timestep(timestep);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+    app.update();
+    let TimestepMode::Fixed { dt, .. } =
+        app.world().resource::<RapierConfiguration>().timestep_mode
+    else {
+        panic!("the physics runs on a fixed timestep");
+    };
+    assert_eq!(dt, timestep.as_secs_f32());
+}
+
+#[test]
+fn arena_plugin_keeps_an_existing_physics_plugin() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule(),
+    ))
+    .insert_resource(RapierConfiguration {
+        gravity: Vec2::new(0.0, -9.81),
+        ..RapierConfiguration::new(1.0)
+    })
+    .add_plugins(ArenaPlugin);
+    app.update();
+    assert_eq!(
+        app.world().resource::<RapierConfiguration>().gravity,
+        Vec2::new(0.0, -9.81)
+    );
+    assert_eq!(count::<With<Wall>>(&mut app), 4);
+}
+
+#[test]
+fn camera_plugin_shakes_the_camera_on_impacts() {
+    isolate_config_dir();
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, GameStatePlugin, CameraPlugin))
+        .add_event::<BallHitBrick>()
+        .add_event::<BrickDestroyed>()
+        .add_event::<BallLost>()
+        .add_event::<PlayerHitObstacle>()
+        .init_resource::<PlayField>()
+        .init_resource::<UiScale>();
+    app.update();
+    assert_eq!(
+        *app.world().resource::<CameraSettings>(),
+        CameraSettings::default()
+    );
+    assert_eq!(count::<With<MainCamera>>(&mut app), 1);
+
+    app.world_mut().send_event(BallLost {
+        ball: Entity::PLACEHOLDER,
+    });
+    app.update();
+    let world = app.world_mut();
+    let rig = world.query::<&CameraRig>().single(world);
+    assert!(rig.trauma > 0.0);
+}
+
+#[test]
+fn camera_plugin_scales_the_play_field_to_the_window() {
+    isolate_config_dir();
+    assert_eq!(
+        "integer".parse::<CameraScaling>().unwrap(),
+        CameraScaling::Integer
+    );
+    assert!("zoom".parse::<CameraScaling>().is_err());
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, GameStatePlugin, CameraPlugin))
+        .add_event::<BallHitBrick>()
+        .add_event::<BrickDestroyed>()
+        .add_event::<BallLost>()
+        .add_event::<PlayerHitObstacle>()
+        .init_resource::<PlayField>()
+        .init_resource::<UiScale>();
+    // Twice the play-field wide, and one and a half times as high
+    let window = Window {
+        resolution: (2560.0, 1080.0).into(),
+        ..default()
+    };
+    app.world_mut().spawn((window, PrimaryWindow));
+
+    let fitted = |app: &mut App, scaling: CameraScaling| {
+        app.insert_resource(scaling);
+        app.update();
+        let world = app.world_mut();
+        let (camera, projection) = world
+            .query_filtered::<(&Camera, &OrthographicProjection), With<MainCamera>>()
+            .single(world);
+        let viewport = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+        (
+            viewport,
+            projection.scaling_mode,
+            app.world().resource::<UiScale>().0,
+        )
+    };
+    let (viewport, scaling_mode, ui_scale) = fitted(&mut app, CameraScaling::Letterbox);
+    assert_eq!(viewport, Some((UVec2::new(320, 0), UVec2::new(1920, 1080))));
+    assert!(matches!(
+        scaling_mode,
+        ScalingMode::Fixed {
+            width: 1280.0,
+            height: 720.0,
+        }
+    ));
+    assert_eq!(ui_scale, 1.5);
+
+    let (viewport, _, ui_scale) = fitted(&mut app, CameraScaling::Integer);
+    assert_eq!(
+        viewport,
+        Some((UVec2::new(640, 180), UVec2::new(1280, 720)))
+    );
+    assert_eq!(ui_scale, 1.0);
+
+    let (viewport, scaling_mode, _) = fitted(&mut app, CameraScaling::Stretch);
+    assert_eq!(viewport, None);
+    assert!(matches!(
+        scaling_mode,
+        ScalingMode::Fixed {
+            width: 1280.0,
+            height: 720.0,
+        }
+    ));
+
+    let (viewport, scaling_mode, ui_scale) = fitted(&mut app, CameraScaling::Fit);
+    assert_eq!(viewport, None);
+    assert!(matches!(
+        scaling_mode,
+        ScalingMode::AutoMin {
+            min_width: 1280.0,
+            min_height: 720.0,
+        }
+    ));
+    assert_eq!(ui_scale, 1.5);
+}
+
+#[test]
+fn block_plugin_rebuilds_the_collider_of_a_resized_block() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), BlockPlugin))
+        .init_asset::<Mesh>();
+    tick_every_update(&mut app);
+
+    let block = {
+        let world = app.world_mut();
+        let bundle = BlockBundle::new(
+            BlockShape::new(Vec2::new(40.0, 20.0)),
+            Handle::default(),
+            Transform::default(),
+            &mut world.resource_mut::<Assets<Mesh>>(),
+        );
+        world.spawn(bundle).id()
+    };
+    // Past the tick it is added on
+    app.update();
+    app.update();
+
+    app.world_mut().get_mut::<BlockShape>(block).unwrap().size = Vec2::new(80.0, 10.0);
+    app.update();
+    let collider = app.world().get::<Collider>(block).unwrap();
+    assert_eq!(
+        collider.as_cuboid().unwrap().half_extents(),
+        Vec2::new(40.0, 5.0)
+    );
+}
+
+#[test]
+fn level_plugin_spawns_and_clears_the_level() {
+    isolate_config_dir();
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        StatesPlugin,
+        GameStatePlugin,
+        MovementPlugin,
+        LevelPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_resource::<LocalPlayers>();
+    app.update();
+    assert!(!app.world().resource::<CurrentLevel>().spawned);
+
+    enter(&mut app, GameState::Loading);
+    for _ in 0..1000 {
+        app.update();
+        if app.world().resource::<CurrentLevel>().spawned {
+            break;
+        }
+    }
+    assert_eq!(
+        *app.world().resource::<State<GameState>>(),
+        GameState::Playing
+    );
+    assert_eq!(count::<With<Player>>(&mut app), 1);
+    assert_eq!(count::<With<Ball>>(&mut app), 1);
+    assert!(count::<With<Obstacle>>(&mut app) > 0);
+
+    enter(&mut app, GameState::MainMenu);
+    assert!(!app.world().resource::<CurrentLevel>().spawned);
+    assert_eq!(count::<With<Player>>(&mut app), 0);
+    assert_eq!(count::<With<Obstacle>>(&mut app), 0);
+}
+
+#[test]
+fn movement_plugin_loads_the_movement_config() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), MovementPlugin))
+        .init_asset::<Level>()
+        .insert_resource(MovementConfig {
+            speed: 1.0,
+            ..default()
+        });
+
+    for _ in 0..1000 {
+        app.update();
+        if app.world().resource::<MovementConfig>().speed != 1.0 {
+            break;
+        }
+    }
+    assert_eq!(
+        app.world().resource::<MovementConfig>().speed,
+        MovementConfig::default().speed
+    );
+}
+
+#[test]
+fn player_plugin_moves_the_paddle() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        StatesPlugin,
+        GameStatePlugin,
+        ArenaPlugin,
+        PlayerPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_resource::<MovementConfig>();
+    tick_every_update(&mut app);
+    assert_eq!(app.world().resource::<LocalPlayers>().count(), 1);
+
+    let player = {
+        let world = app.world_mut();
+        let bundle = PlayerBundle::new(
+            PlayerId(0),
+            Vec2::ZERO,
+            MovementModel::Arcade,
+            Handle::default(),
+            &mut world.resource_mut::<Assets<Mesh>>(),
+        );
+        world.spawn(bundle).id()
+    };
+    app.world_mut()
+        .get_mut::<ActionState<Action>>(player)
+        .unwrap()
+        .press(&Action::RIGHT);
+    enter(&mut app, GameState::Playing);
+    for _ in 0..10 {
+        app.update();
+    }
+
+    assert!(app.world().get::<Transform>(player).unwrap().translation.x > 0.0);
+}
+
+#[test]
+fn bindings_plugin_rebinds_a_key() {
+    isolate_config_dir();
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin, BindingsPlugin))
+        .init_resource::<LocalPlayers>();
+    let player = app
+        .world_mut()
+        .spawn((Player, PlayerId(0), InputMap::<Action>::default()))
+        .id();
+    app.update();
+
+    let bindings = app.world().resource::<KeyBindings>().clone();
+    assert_eq!(
+        *app.world().get::<InputMap<Action>>(player).unwrap(),
+        bindings.input_map(PlayerId(0), None)
+    );
+
+    // J is a key of the second player
+    app.world_mut().resource_mut::<Rebinding>().action = Some((PlayerId(0), Action::LEFT));
+    tap(&mut app, KeyCode::KeyJ);
+    app.update();
+    assert!(app.world().resource::<Rebinding>().conflict.is_some());
+    assert_eq!(*app.world().resource::<KeyBindings>(), bindings);
+
+    app.world_mut().resource_mut::<Rebinding>().action = Some((PlayerId(0), Action::LEFT));
+    tap(&mut app, KeyCode::KeyZ);
+    app.update();
+
+    let bindings = app.world().resource::<KeyBindings>();
+    assert_eq!(bindings.keys(PlayerId(0), Action::LEFT), [KeyCode::KeyZ]);
+    assert_eq!(
+        *app.world().get::<InputMap<Action>>(player).unwrap(),
+        bindings.input_map(PlayerId(0), None)
+    );
+}
+
+#[test]
+fn collision_plugin_sorts_out_the_contacts_of_the_balls() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        StatesPlugin,
+        GameStatePlugin,
+        CollisionPlugin,
+    ))
+    .add_event::<CollisionEvent>()
+    .add_event::<BallLost>();
+    tick_every_update(&mut app);
+    enter(&mut app, GameState::Playing);
+
+    let ball = app.world_mut().spawn(Ball).id();
+    let brick = app.world_mut().spawn(Obstacle).id();
+    let kill_zone = app.world_mut().spawn(KillZone).id();
+    app.world_mut().send_event_batch([
+        CollisionEvent::Started(brick, ball, CollisionEventFlags::empty()),
+        CollisionEvent::Started(ball, kill_zone, CollisionEventFlags::empty()),
+    ]);
+    app.update();
+
+    let hits = events::<BallHitBrick>(&app);
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].ball, hits[0].brick), (ball, brick));
+    let lost = events::<BallLost>(&app);
+    assert_eq!(lost.len(), 1);
+    assert_eq!(lost[0].ball, ball);
+    assert!(events::<BallHitPlayer>(&app).is_empty());
+}
+
+#[test]
+fn ball_plugin_keeps_the_ball_speed_and_last_hit() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, GameStatePlugin, BallPlugin))
+        .add_event::<BallHitPlayer>();
+    tick_every_update(&mut app);
+    enter(&mut app, GameState::Playing);
+
+    let ball = app
+        .world_mut()
+        .spawn((
+            Ball,
+            LastHitBy::default(),
+            Transform::default(),
+            Velocity::linear(Vec2::new(10.0, 0.0)),
+        ))
+        .id();
+    let player = app
+        .world_mut()
+        .spawn((
+            Player,
+            Transform::from_xyz(0.0, -20.0, 0.0),
+            BlockShape::new(Vec2::new(120.0, 20.0)),
+            PlayerMotion::default(),
+        ))
+        .id();
+    app.update();
+
+    let speed = app.world().resource::<BallSpeed>().0;
+    let velocity = app.world().get::<Velocity>(ball).unwrap().linvel;
+    assert!((velocity.length() - speed).abs() < 1e-3);
+    assert!(velocity.y.abs() >= speed * MIN_VERTICAL_SPEED - 1e-3);
+
+    app.world_mut().send_event(BallHitPlayer { ball, player });
+    app.update();
+    assert_eq!(app.world().get::<LastHitBy>(ball).unwrap().0, Some(player));
+    // Sent up, away from the paddle below it
+    assert!(app.world().get::<Velocity>(ball).unwrap().linvel.y > 0.0);
+}
+
+#[test]
+fn obstacle_plugin_damages_and_destroys_bricks() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        StatesPlugin,
+        GameStatePlugin,
+        ObstaclePlugin,
+    ))
+    .init_asset::<ColorMaterial>()
+    .add_event::<BallHitBrick>()
+    .add_event::<LaserHitBrick>();
+    tick_every_update(&mut app);
+    enter(&mut app, GameState::Playing);
+
+    let player = app.world_mut().spawn(Player).id();
+    let ball = app.world_mut().spawn((Ball, LastHitBy(Some(player)))).id();
+    let brick = app
+        .world_mut()
+        .spawn((
+            Obstacle,
+            Transform::from_xyz(10.0, 20.0, 0.0),
+            HitPoints::new(2),
+        ))
+        .id();
+
+    app.world_mut().send_event(BallHitBrick { ball, brick });
+    app.update();
+    assert_eq!(app.world().get::<HitPoints>(brick).unwrap().remaining, 1);
+    assert!(events::<BrickDestroyed>(&app).is_empty());
+
+    app.world_mut().send_event(BallHitBrick { ball, brick });
+    app.update();
+    assert!(app.world().get_entity(brick).is_none());
+    let destroyed = events::<BrickDestroyed>(&app);
+    assert_eq!(destroyed.len(), 1);
+    assert_eq!(destroyed[0].player, Some(player));
+    assert_eq!(destroyed[0].position, Vec2::new(10.0, 20.0));
+}
+
+#[test]
+fn obstacle_plugin_leaves_indestructible_bricks_alone() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        StatesPlugin,
+        GameStatePlugin,
+        ObstaclePlugin,
+    ))
+    .init_asset::<ColorMaterial>()
+    .add_event::<BallHitBrick>()
+    .add_event::<LaserHitBrick>();
+    tick_every_update(&mut app);
+    enter(&mut app, GameState::Playing);
+
+    let color = Color::srgb(0.5, 0.5, 0.5);
+    let material = app
+        .world_mut()
+        .resource_mut::<Assets<ColorMaterial>>()
+        .add(color);
+    let ball = app.world_mut().spawn((Ball, LastHitBy(None))).id();
+    // The catalogue lets indestructible bricks have no hit points
+    let brick = app
+        .world_mut()
+        .spawn((
+            Obstacle,
+            Indestructible,
+            Transform::default(),
+            HitPoints::new(0),
+            BrickColor(color),
+            material.clone(),
+        ))
+        .id();
+
+    app.world_mut().send_event(BallHitBrick { ball, brick });
+    app.update();
+    assert!(app.world().get_entity(brick).is_some());
+    assert_eq!(app.world().get::<HitPoints>(brick).unwrap().remaining, 0);
+    let materials = app.world().resource::<Assets<ColorMaterial>>();
+    assert_eq!(materials.get(&material).unwrap().color, color);
+}
+
+#[test]
+fn score_plugin_scores_bricks_and_takes_lives() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        StatesPlugin,
+        GameStatePlugin,
+        ScorePlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<Level>()
+    .init_resource::<LocalPlayers>()
+    .add_event::<BrickDestroyed>()
+    .add_event::<BallHitPlayer>()
+    .add_event::<BallLost>();
+    tick_every_update(&mut app);
+    current_level(&mut app, "Test");
+    enter(&mut app, GameState::Playing);
+    assert_eq!(app.world().resource::<Lives>().0, STARTING_LIVES);
+
+    let player = app
+        .world_mut()
+        .spawn((Player, PlayerId(0), PlayerScore(0), Transform::default()))
+        .id();
+    let destroyed = BrickDestroyed {
+        player: Some(player),
+        position: Vec2::ZERO,
+    };
+    app.world_mut().send_event_batch([destroyed, destroyed]);
+    app.update();
+    let score = *app.world().resource::<Score>();
+    assert_eq!(score.points, 2 * BRICK_POINTS);
+    assert_eq!(score.combo, 2);
+    assert_eq!(
+        app.world().get::<PlayerScore>(player).unwrap().0,
+        score.points
+    );
+
+    let world = app.world_mut();
+    let texts: Vec<String> = world
+        .query::<&Text>()
+        .iter(world)
+        .map(|text| text.sections[0].value.clone())
+        .collect();
+    assert!(texts.contains(&format!("Score {}", score.points)));
+    assert!(texts.contains(&format!("P1 {}", score.points)));
+
+    // A new ball is served from the paddle
+    let ball = app.world_mut().spawn((Ball, LastHitBy(Some(player)))).id();
+    app.world_mut().send_event(BallLost { ball });
+    app.update();
+    assert_eq!(app.world().resource::<Lives>().0, STARTING_LIVES - 1);
+    assert_eq!(app.world().resource::<Score>().combo, 0);
+    assert!(app.world().get_entity(ball).is_none());
+    assert_eq!(count::<With<Ball>>(&mut app), 1);
+}
+
+#[test]
+fn power_up_plugin_applies_caught_power_ups() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        StatesPlugin,
+        GameStatePlugin,
+        PowerUpPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .insert_resource(GameRng::new(1))
+    .init_resource::<Lives>()
+    .init_resource::<BallSpeed>()
+    .init_resource::<PlayField>()
+    .add_event::<BrickDestroyed>()
+    .add_event::<BallHitPlayer>()
+    .add_event::<LaserHitBrick>()
+    .add_event::<PlayerCollectedPowerUp>();
+    tick_every_update(&mut app);
+    enter(&mut app, GameState::Playing);
+    assert!(app.world().resource::<ActiveEffects>().remaining.is_empty());
+
+    let player = app
+        .world_mut()
+        .spawn((
+            Player,
+            Transform::default(),
+            BlockShape::new(Vec2::new(120.0, 20.0)),
+        ))
+        .id();
+    let slow_ball = app
+        .world_mut()
+        .spawn((PowerUp(PowerUpKind::SlowBall), Transform::default()))
+        .id();
+    let extra_life = app
+        .world_mut()
+        .spawn((PowerUp(PowerUpKind::ExtraLife), Transform::default()))
+        .id();
+    app.world_mut().send_event_batch([
+        PlayerCollectedPowerUp {
+            player,
+            power_up: slow_ball,
+        },
+        PlayerCollectedPowerUp {
+            player,
+            power_up: extra_life,
+        },
+    ]);
+    app.update();
+
+    assert!(app.world().get_entity(slow_ball).is_none());
+    assert!(app.world().get_entity(extra_life).is_none());
+    assert!(app
+        .world()
+        .resource::<ActiveEffects>()
+        .is_active(PowerUpKind::SlowBall));
+    assert!(app.world().resource::<BallSpeed>().0 < BallSpeed::default().0);
+    assert_eq!(app.world().resource::<Lives>().0, STARTING_LIVES + 1);
+}
+
+#[test]
+fn high_score_plugin_enters_a_high_score() {
+    isolate_config_dir();
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        InputPlugin,
+        StatesPlugin,
+        GameStatePlugin,
+        HighScorePlugin,
+    ))
+    .init_asset::<Level>()
+    .insert_resource(Score {
+        points: 500,
+        combo: 0,
+    })
+    .insert_resource(PlayTime(Duration::from_secs(65)));
+    current_level(&mut app, "High score test");
+    app.update();
+    assert!(app
+        .world()
+        .resource::<HighScores>()
+        .entries("High score test")
+        .is_empty());
+
+    enter(&mut app, GameState::GameOver);
+    let entry = app.world().resource::<InitialsEntry>();
+    assert_eq!(entry.score, 500);
+
+    for key in [KeyCode::KeyA, KeyCode::KeyB, KeyCode::Enter] {
+        tap(&mut app, key);
+    }
+    app.update();
+
+    assert!(app.world().get_resource::<InitialsEntry>().is_none());
+    let entries = app
+        .world()
+        .resource::<HighScores>()
+        .entries("High score test");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "AB");
+    assert_eq!(entries[0].score, 500);
+    assert_eq!(entries[0].time, Duration::from_secs(65));
+}
+
+#[test]
+fn replay_plugin_records_the_inputs_of_a_level() {
+    isolate_config_dir();
+    let path = storage::config_dir()
+        .unwrap()
+        .join("plugin-test.replay.ron");
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, GameStatePlugin, ReplayPlugin))
+        .insert_resource(ReplayMode::Record(path.clone()))
+        .insert_resource(GameRng::new(3))
+        .init_resource::<LocalPlayers>()
+        .init_resource::<TickRate>()
+        .insert_resource(CurrentLevel {
+            handle: Handle::default(),
+            spawned: true,
+        });
+    tick_every_update(&mut app);
+    app.update();
+    let recording = app.world().resource::<Recording>();
+    assert_eq!(recording.seed, 3);
+    assert_eq!(recording.players, 1);
+
+    let mut action_state = ActionState::<Action>::default();
+    action_state.press(&Action::LEFT);
+    app.world_mut().spawn((Player, PlayerId(0), action_state));
+    enter(&mut app, GameState::Playing);
+    for _ in 0..3 {
+        app.update();
+    }
+    let recorded = app.world().resource::<Recording>().ticks.len();
+    assert!(recorded >= 3);
+    let last = app.world().resource::<Recording>().ticks.last().unwrap();
+    assert_eq!(last[0].pressed, [Action::LEFT]);
+
+    // Saved once the level is over
+    enter(&mut app, GameState::GameOver);
+    assert!(app.world().get_resource::<Recording>().is_none());
+    let saved = storage::load::<Recording>(&path).unwrap().unwrap();
+    assert_eq!(saved.ticks.len(), recorded);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn replay_plugin_stops_recording_and_reseeds_on_restart() {
+    isolate_config_dir();
+    let path = storage::config_dir()
+        .unwrap()
+        .join("plugin-test-restart.replay.ron");
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, GameStatePlugin, ReplayPlugin))
+        .insert_resource(ReplayMode::Record(path.clone()))
+        .insert_resource(GameRng::new(3))
+        .init_resource::<LocalPlayers>()
+        .init_resource::<TickRate>()
+        .insert_resource(CurrentLevel {
+            handle: Handle::default(),
+            spawned: true,
+        });
+    tick_every_update(&mut app);
+    app.update();
+    app.world_mut()
+        .spawn((Player, PlayerId(0), ActionState::<Action>::default()));
+    enter(&mut app, GameState::Playing);
+    for _ in 0..3 {
+        app.update();
+    }
+    let recorded = app.world().resource::<Recording>().ticks.len();
+
+    // Restarting goes back to loading without ending the level
+    enter(&mut app, GameState::Loading);
+    assert!(app.world().get_resource::<Recording>().is_none());
+    let saved = storage::load::<Recording>(&path).unwrap().unwrap();
+    assert_eq!(saved.ticks.len(), recorded);
+    let seed = app.world().resource::<GameRng>().seed();
+    assert_ne!(seed, 3);
+
+    // The next seed follows from the first one
+    let mut expected = GameRng::new(3);
+    assert_eq!(seed, rand::RngCore::next_u64(&mut expected));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn menu_plugin_follows_the_menu_buttons() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        InputPlugin,
+        StatesPlugin,
+        GameStatePlugin,
+        MenuPlugin,
+    ));
+    app.update();
+    let world = app.world_mut();
+    let mut buttons: Vec<MenuButton> = world.query::<&MenuButton>().iter(world).copied().collect();
+    buttons.sort_by_key(|button| format!("{button:?}"));
+    assert_eq!(
+        buttons,
+        [MenuButton::HighScores, MenuButton::Play, MenuButton::Quit]
+    );
+
+    let world = app.world_mut();
+    let quit = world
+        .query::<(Entity, &MenuButton)>()
+        .iter(world)
+        .find(|(_, button)| **button == MenuButton::Quit)
+        .map(|(entity, _)| entity)
+        .unwrap();
+    app.world_mut()
+        .entity_mut(quit)
+        .insert(Interaction::Pressed);
+    app.update();
+    assert_eq!(app.should_exit(), Some(AppExit::Success));
+
+    // Enter presses the default button, Play
+    tap(&mut app, KeyCode::Enter);
+    app.update();
+    assert_eq!(
+        *app.world().resource::<State<GameState>>(),
+        GameState::Loading
+    );
+    assert_eq!(count::<With<MenuButton>>(&mut app), 0);
+}
+
+#[test]
+fn pause_plugin_stops_and_resumes_time() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, GameStatePlugin, PausePlugin))
+        .insert_resource(RapierConfiguration::new(100.0));
+    enter(&mut app, GameState::Playing);
+    let player = app
+        .world_mut()
+        .spawn((Player, ActionState::<Action>::default()))
+        .id();
+
+    let press_pause = |app: &mut App| {
+        let mut action_state = app
+            .world_mut()
+            .get_mut::<ActionState<Action>>(player)
+            .unwrap();
+        action_state.press(&Action::PAUSE);
+        app.update();
+        let mut action_state = app
+            .world_mut()
+            .get_mut::<ActionState<Action>>(player)
+            .unwrap();
+        action_state.release(&Action::PAUSE);
+        app.update();
+    };
+
+    press_pause(&mut app);
+    assert_eq!(
+        *app.world().resource::<State<GameState>>(),
+        GameState::Paused
+    );
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+    assert!(
+        !app.world()
+            .resource::<RapierConfiguration>()
+            .physics_pipeline_active
+    );
+
+    press_pause(&mut app);
+    assert_eq!(
+        *app.world().resource::<State<GameState>>(),
+        GameState::Playing
+    );
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+    assert!(
+        app.world()
+            .resource::<RapierConfiguration>()
+            .physics_pipeline_active
+    );
+}
+
+#[test]
+fn overlay_plugin_toggles_click_through() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin, OverlayPlugin));
+    let window = app
+        .world_mut()
+        .spawn((Window::default(), PrimaryWindow))
+        .id();
+    app.update();
+    assert_eq!(
+        *app.world().resource::<DisplayMode>(),
+        DisplayMode::Windowed
+    );
+
+    // Only in overlay mode
+    tap(&mut app, CLICK_THROUGH_KEY);
+    assert!(app.world().get::<Window>(window).unwrap().cursor.hit_test);
+
+    app.insert_resource(DisplayMode::Overlay);
+    app.update();
+    tap(&mut app, CLICK_THROUGH_KEY);
+    assert!(!app.world().get::<Window>(window).unwrap().cursor.hit_test);
+    tap(&mut app, CLICK_THROUGH_KEY);
+    assert!(app.world().get::<Window>(window).unwrap().cursor.hit_test);
+}
@@ -0,0 +1,145 @@
+use std::{path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+use blockbracker::{
+    ball::Ball,
+    headless::headless_app,
+    level::CurrentLevel,
+    player::{Player, PlayerId},
+    replay::{Recording, Replay, ReplayMode},
+    rng::GameRng,
+    score::{Lives, Score},
+    storage, Action, GameState,
+};
+use leafwing_input_manager::prelude::*;
+
+const TIMESTEP: Duration = Duration::from_micros(15_625);
+const TICKS: usize = 600;
+
+/// What a run ended with, compared between the recorded run and its replay.
+#[derive(Debug, PartialEq)]
+struct FinalState {
+    balls: Vec<Vec3>,
+    players: Vec<(PlayerId, Transform)>,
+    points: u32,
+    lives: u32,
+}
+
+impl FinalState {
+    fn of(app: &mut App) -> Self {
+        let world = app.world_mut();
+        let mut balls: Vec<_> = world
+            .query_filtered::<&Transform, With<Ball>>()
+            .iter(world)
+            .map(|transform| transform.translation)
+            .collect();
+        balls.sort_by(|a, b| a.to_array().partial_cmp(&b.to_array()).unwrap());
+        let mut players: Vec<_> = world
+            .query_filtered::<(&PlayerId, &Transform), With<Player>>()
+            .iter(world)
+            .map(|(id, transform)| (*id, *transform))
+            .collect();
+        players.sort_by_key(|(id, _)| *id);
+
+        Self {
+            balls,
+            players,
+            points: world.resource::<Score>().points,
+            lives: world.resource::<Lives>().0,
+        }
+    }
+}
+
+fn recording_path() -> PathBuf {
+    std::env::temp_dir().join(format!("blockbracker-replay-{}.ron", std::process::id()))
+}
+
+fn update_until_spawned(app: &mut App) {
+    for _ in 0..1000 {
+        app.update();
+        if app.world().resource::<CurrentLevel>().spawned {
+            return;
+        }
+    }
+    panic!("the level never spawned");
+}
+
+/// Sweeps the paddle from side to side, turning it every now and then.
+fn press_scripted_inputs(app: &mut App, tick: usize) {
+    let world = app.world_mut();
+    let mut players = world.query_filtered::<&mut ActionState<Action>, With<Player>>();
+    for mut action_state in players.iter_mut(world) {
+        let (press, release) = if tick % 90 < 45 {
+            (Action::RIGHT, Action::LEFT)
+        } else {
+            (Action::LEFT, Action::RIGHT)
+        };
+        action_state.press(&press);
+        action_state.release(&release);
+        if tick % 100 < 10 {
+            action_state.press(&Action::RRIGHT);
+        } else {
+            action_state.release(&Action::RRIGHT);
+        }
+    }
+}
+
+fn record(path: PathBuf) -> FinalState {
+    let mut app = headless_app(TIMESTEP);
+    app.insert_resource(GameRng::new(7))
+        .insert_resource(ReplayMode::Record(path));
+    update_until_spawned(&mut app);
+
+    for tick in 0..TICKS {
+        if *app.world().resource::<State<GameState>>() != GameState::Playing {
+            break;
+        }
+        press_scripted_inputs(&mut app, tick);
+        app.update();
+    }
+    // Saves the recording if the level did not end first, this update still runs and
+    // records a tick
+    app.world_mut().send_event(AppExit::Success);
+    app.update();
+
+    FinalState::of(&mut app)
+}
+
+fn replay(path: PathBuf) -> FinalState {
+    let mut app = headless_app(TIMESTEP);
+    app.insert_resource(ReplayMode::Replay(path));
+    update_until_spawned(&mut app);
+
+    for _ in 0..TICKS * 2 {
+        let replayed = app
+            .world()
+            .get_resource::<Replay>()
+            .is_none_or(|replay| replay.tick == replay.recording.ticks.len());
+        if replayed {
+            let state = FinalState::of(&mut app);
+            // The replay exits on the tick after its last one
+            app.update();
+            assert_eq!(app.should_exit(), Some(AppExit::Success));
+            return state;
+        }
+        app.update();
+    }
+    panic!("the replay never finished");
+}
+
+#[test]
+fn replay_reproduces_the_recorded_run() {
+    let path = recording_path();
+    let recorded = record(path.clone());
+
+    let recording = storage::load::<Recording>(&path)
+        .expect("the recording is readable")
+        .expect("the recording was saved");
+    assert_eq!(recording.seed, 7);
+    assert!(!recording.ticks.is_empty());
+
+    let replayed = replay(path.clone());
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(replayed, recorded);
+}